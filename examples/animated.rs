@@ -1,5 +1,9 @@
+use std::time::Duration;
+
 use bevy::{asset::AssetPlugin, log::LogPlugin, prelude::*};
-use bevy_streamdeck::{StreamDeck, StreamDeckPlugin};
+use bevy_streamdeck::{
+    KeyAnimation, KeyPressed, StreamDeckKey, StreamDeckKeyIndex, StreamDeckPlugin,
+};
 
 fn main() {
     App::new()
@@ -9,42 +13,27 @@ fn main() {
             ImagePlugin::default(),
             LogPlugin::default(),
         ))
-        .add_plugins(StreamDeckPlugin)
-        .insert_resource(Time::<Fixed>::from_seconds(0.075))
-        .add_systems(Startup, load_asset)
-        .add_systems(FixedUpdate, change_image)
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Startup, spawn_animation)
+        .add_systems(Update, pause_on_press)
         .run();
 }
 
-#[derive(Resource)]
-struct Animated([Handle<Image>; 11], usize);
-
-fn load_asset(mut commands: Commands, asset_server: Res<AssetServer>) {
-    commands.insert_resource(Animated(
-        [
-            asset_server.load("p1_walk01.png"),
-            asset_server.load("p1_walk02.png"),
-            asset_server.load("p1_walk03.png"),
-            asset_server.load("p1_walk04.png"),
-            asset_server.load("p1_walk05.png"),
-            asset_server.load("p1_walk06.png"),
-            asset_server.load("p1_walk07.png"),
-            asset_server.load("p1_walk08.png"),
-            asset_server.load("p1_walk09.png"),
-            asset_server.load("p1_walk10.png"),
-            asset_server.load("p1_walk11.png"),
-        ],
-        0,
+fn spawn_animation(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let frames = (1..=11).map(|i| asset_server.load(format!("p1_walk{i:02}.png")));
+    commands.spawn((
+        StreamDeckKeyIndex(StreamDeckKey::from(0)),
+        KeyAnimation::from_images(frames, Duration::from_millis(75)),
     ));
 }
 
-fn change_image(
-    streamdeck: Res<StreamDeck>,
-    mut animated: ResMut<Animated>,
-    images: Res<Assets<Image>>,
-) {
-    if let Some(image) = images.get(&animated.0[animated.1]) {
-        streamdeck.set_key_image(0, &image);
-        animated.1 = (animated.1 + 1) % animated.0.len();
+/// Pause the animation while its key is pressed.
+fn pause_on_press(mut animations: Query<(&mut KeyAnimation, Has<KeyPressed>)>) {
+    for (mut animation, pressed) in &mut animations {
+        if pressed && !animation.is_paused() {
+            animation.pause();
+        } else if !pressed && animation.is_paused() {
+            animation.resume();
+        }
     }
 }
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    asset::RenderAssetUsages,
+    camera::RenderTarget,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_streamdeck::{DeviceId, GridMode, RenderToDeck, StreamDeckPlugin};
+
+// No window is opened, this also works with a software renderer
+fn main() {
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    ..default()
+                })
+                .disable::<WinitPlugin>(),
+        )
+        .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+            1.0 / 60.0,
+        )))
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Startup, setup)
+        .add_systems(Update, rotate)
+        .run();
+}
+
+#[derive(Component)]
+struct Rotating;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let mut target = Image::new_fill(
+        Extent3d {
+            width: 480,
+            height: 320,
+            ..default()
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    target.texture_descriptor.usage |=
+        TextureUsages::COPY_SRC | TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+    let target = images.add(target);
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(target.clone().into()),
+        Transform::from_xyz(0.0, 1.5, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::default())),
+        MeshMaterial3d(materials.add(Color::srgb(0.8, 0.4, 0.1))),
+        Rotating,
+    ));
+    commands.spawn((PointLight::default(), Transform::from_xyz(2.0, 4.0, 4.0)));
+
+    commands.spawn(
+        RenderToDeck::device(target, DeviceId::default(), GridMode::default())
+            .with_interval(Duration::from_millis(50)),
+    );
+}
+
+fn rotate(time: Res<Time>, mut cubes: Query<&mut Transform, With<Rotating>>) {
+    for mut transform in &mut cubes {
+        transform.rotate_y(time.delta_secs());
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_streamdeck::{
+    Chord, ChordId, StreamDeck, StreamDeckChord, StreamDeckChords, StreamDeckInput,
+    StreamDeckPlugin,
+};
+
+#[derive(Resource)]
+struct Chords {
+    reset: ChordId,
+    delete: ChordId,
+}
+
+fn main() {
+    App::new()
+        .add_plugins((MinimalPlugins, LogPlugin::default()))
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Startup, register_chords)
+        .add_systems(Update, (show_presses, fire_chords))
+        .run();
+}
+
+fn register_chords(mut commands: Commands, mut chords: ResMut<StreamDeckChords>) {
+    let reset = chords.register(Chord::new([0, 1]));
+    // Keys 0 and 4 are only pressed on their own if the chord didn't fire
+    let delete = chords.register(
+        Chord::new([0, 4])
+            .with_window(Duration::from_millis(300))
+            .suppressing(),
+    );
+    commands.insert_resource(Chords { reset, delete });
+}
+
+fn show_presses(mut inputs: MessageReader<StreamDeckInput>) {
+    for input in inputs.read() {
+        info!("{:?}", input);
+    }
+}
+
+fn fire_chords(
+    streamdeck: Res<StreamDeck>,
+    chords: Res<Chords>,
+    mut fired: MessageReader<StreamDeckChord>,
+) {
+    for StreamDeckChord(chord) in fired.read() {
+        if *chord == chords.reset {
+            info!("reset");
+            streamdeck.reset();
+        } else if *chord == chords.delete {
+            info!("delete");
+            streamdeck.set_key_color(0, Color::srgb(1.0, 0.0, 0.0));
+            streamdeck.set_key_color(4, Color::srgb(1.0, 0.0, 0.0));
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::time::Duration;
 use bevy::{
     app::AppExit, asset::AssetPlugin, log::LogPlugin, prelude::*, time::common_conditions::on_timer,
 };
-use bevy_streamdeck::{ImageMode, StreamDeck, StreamDeckPlugin};
+use bevy_streamdeck::{ImageMode, KeyImageBindings, StreamDeck, StreamDeckPlugin};
 use rand::RngExt;
 
 fn main() {
@@ -14,7 +14,7 @@ fn main() {
             ImagePlugin::default(),
             LogPlugin::default(),
         ))
-        .add_plugins(StreamDeckPlugin)
+        .add_plugins(StreamDeckPlugin::default())
         .add_systems(Startup, load_asset)
         .add_systems(
             Update,
@@ -28,7 +28,6 @@ fn main() {
             Update,
             (invert_image, background_image).run_if(on_timer(Duration::from_secs_f32(1.0))),
         )
-        .add_systems(Update, setup)
         .run();
 }
 
@@ -37,9 +36,9 @@ fn change_color(
     time: Res<Time>,
     mut app_exit: MessageWriter<AppExit>,
 ) {
-    if let Some(_) = streamdeck.kind() {
+    if streamdeck.kind().is_some() {
         let color = Color::hsl(
-            (((time.elapsed_secs() / 5.0).cos() + 1.0) / 2.0) as f32 * 360.0,
+            ((time.elapsed_secs() / 5.0).cos() + 1.0) / 2.0 * 360.0,
             1.0,
             0.5,
         );
@@ -56,7 +55,11 @@ struct Animated([Handle<Image>; 11], usize);
 #[derive(Resource)]
 struct Logos(Handle<Image>, Handle<Image>, Handle<Image>);
 
-fn load_asset(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn load_asset(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut bindings: ResMut<KeyImageBindings>,
+) {
     commands.insert_resource(Animated(
         [
             asset_server.load("p1_walk01.png"),
@@ -73,11 +76,15 @@ fn load_asset(mut commands: Commands, asset_server: Res<AssetServer>) {
         ],
         0,
     ));
-    commands.insert_resource(Logos(
+    let logos = Logos(
         asset_server.load("bevy.png"),
         asset_server.load("vleue.png"),
         asset_server.load("birdoggo.png"),
-    ))
+    );
+    bindings.bind(2, logos.1.clone());
+    bindings.bind(3, logos.0.clone());
+    bindings.bind(5, logos.2.clone());
+    commands.insert_resource(logos);
 }
 
 fn animated(
@@ -86,7 +93,7 @@ fn animated(
     images: Res<Assets<Image>>,
 ) {
     if let Some(image) = images.get(&animated.0[animated.1]) {
-        streamdeck.set_key_image(0, &image);
+        streamdeck.set_key_image(0, image);
         animated.1 = (animated.1 + 1) % animated.0.len();
     }
 }
@@ -97,62 +104,37 @@ fn invert_image(
     images: Res<Assets<Image>>,
     mut inverted: Local<bool>,
 ) {
-    if let Some(image) = images.get(&logos.1) {
-        if let Some(_) = streamdeck.kind() {
-            streamdeck.set_key_image_with_mode(
-                2,
-                &image,
-                ImageMode {
-                    invert: *inverted,
-                    ..Default::default()
-                },
-            );
-            *inverted = !*inverted;
-        }
+    if let Some(image) = images.get(&logos.1)
+        && streamdeck.kind().is_some()
+    {
+        streamdeck.set_key_image_with_mode(
+            2,
+            image,
+            ImageMode {
+                invert: *inverted,
+                ..Default::default()
+            },
+        );
+        *inverted = !*inverted;
     }
 }
 
 fn background_image(streamdeck: Res<StreamDeck>, logos: Res<Logos>, images: Res<Assets<Image>>) {
-    if let Some(image) = images.get(&logos.2) {
-        if let Some(_) = streamdeck.kind() {
-            let mut rng = rand::rng();
-            let color = Color::linear_rgb(rng.random(), rng.random(), rng.random());
-
-            streamdeck.set_key_color(4, color);
+    if let Some(image) = images.get(&logos.2)
+        && streamdeck.kind().is_some()
+    {
+        let mut rng = rand::rng();
+        let color = Color::linear_rgb(rng.random(), rng.random(), rng.random());
 
-            streamdeck.set_key_image_with_mode(
-                5,
-                &image,
-                ImageMode {
-                    background: Some(color),
-                    ..Default::default()
-                },
-            );
-        }
-    }
-}
+        streamdeck.set_key_color(4, color);
 
-fn setup(
-    streamdeck: Res<StreamDeck>,
-    logos: Res<Logos>,
-    images: Res<Assets<Image>>,
-    mut done: Local<usize>,
-) {
-    if *done < 3 {
-        *done = 0;
-        if let Some(_) = streamdeck.kind() {
-            if let Some(image) = images.get(&logos.1) {
-                streamdeck.set_key_image(2, &image);
-                *done += 1;
-            }
-            if let Some(image) = images.get(&logos.0) {
-                streamdeck.set_key_image(3, &image);
-                *done += 1;
-            }
-            if let Some(image) = images.get(&logos.2) {
-                streamdeck.set_key_image(5, &image);
-                *done += 1;
-            }
-        }
+        streamdeck.set_key_image_with_mode(
+            5,
+            image,
+            ImageMode {
+                background: Some(color),
+                ..Default::default()
+            },
+        );
     }
 }
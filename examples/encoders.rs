@@ -0,0 +1,50 @@
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_streamdeck::{
+    EncoderRotation, StreamDeck, StreamDeckEncoder, StreamDeckInput, StreamDeckPlugin, encoders,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((MinimalPlugins, LogPlugin::default()))
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Update, (print_plus_events, reset_on_press))
+        .run();
+}
+
+fn print_plus_events(
+    mut streamdeck_input: MessageReader<StreamDeckInput>,
+    rotation: Res<EncoderRotation>,
+) {
+    for event in streamdeck_input.read() {
+        match event {
+            StreamDeckInput::EncoderTwist(encoder, ticks) => info!(
+                "encoder {} turned {} ticks, total {}",
+                encoder.encoder,
+                ticks,
+                rotation.total(*encoder)
+            ),
+            StreamDeckInput::Touch(_, touch) => match touch.swipe_vector() {
+                Some(swipe) => info!("swiped {} from {}", swipe, touch.position()),
+                None => info!("touched {:?}", touch),
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Reset the rotation of an encoder when it is pushed.
+fn reset_on_press(
+    streamdeck: Res<StreamDeck>,
+    encoder_buttons: Res<ButtonInput<StreamDeckEncoder>>,
+    mut rotation: ResMut<EncoderRotation>,
+) {
+    let Some(kind) = streamdeck.kind() else {
+        return;
+    };
+    for i in 0..encoders(kind) {
+        if encoder_buttons.just_pressed(StreamDeckEncoder::from(i)) {
+            info!("encoder {} reset", i);
+            rotation.reset(i);
+        }
+    }
+}
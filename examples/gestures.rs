@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_streamdeck::{GestureSettings, StreamDeck, StreamDeckGesture, StreamDeckPlugin};
+
+fn main() {
+    App::new()
+        .add_plugins((MinimalPlugins, LogPlugin::default()))
+        .add_plugins(StreamDeckPlugin::default())
+        .insert_resource(GestureSettings {
+            long_press: Duration::from_millis(800),
+            ..default()
+        })
+        .add_systems(Update, show_gestures)
+        .run();
+}
+
+fn show_gestures(streamdeck: Res<StreamDeck>, mut gestures: MessageReader<StreamDeckGesture>) {
+    for gesture in gestures.read() {
+        info!("{:?}", gesture);
+        match gesture {
+            StreamDeckGesture::Tap(key) => {
+                streamdeck.set_key_color(*key, Color::srgb(0.0, 0.0, 1.0))
+            }
+            StreamDeckGesture::DoubleTap(key) => {
+                streamdeck.set_key_color(*key, Color::srgb(0.0, 1.0, 0.0))
+            }
+            StreamDeckGesture::LongPress(key) => {
+                streamdeck.set_key_color(*key, Color::srgb(1.0, 0.0, 0.0))
+            }
+            StreamDeckGesture::Repeat(key, count) => {
+                streamdeck.set_key_color(*key, Color::srgb(1.0, (*count % 10) as f32 / 10.0, 0.0))
+            }
+        }
+    }
+}
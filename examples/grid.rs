@@ -0,0 +1,59 @@
+use bevy::{asset::AssetPlugin, log::LogPlugin, prelude::*};
+use bevy_streamdeck::{
+    GridMode, ImageMode, ImageResize, StreamDeck, StreamDeckInput, StreamDeckPlugin,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            ImagePlugin::default(),
+            LogPlugin::default(),
+        ))
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Startup, load_asset)
+        .add_systems(Update, display_logo)
+        .run();
+}
+
+#[derive(Resource)]
+struct Logo(Handle<Image>);
+
+fn load_asset(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(Logo(asset_server.load("bevy.png")));
+}
+
+fn display_logo(
+    streamdeck: Res<StreamDeck>,
+    logo: Res<Logo>,
+    images: Res<Assets<Image>>,
+    mut streamdeck_input: MessageReader<StreamDeckInput>,
+    mut displayed: Local<bool>,
+) {
+    if streamdeck_input
+        .read()
+        .any(|event| matches!(event, StreamDeckInput::Connected(..)))
+    {
+        *displayed = false;
+    }
+    if *displayed {
+        return;
+    }
+    if let Some(image) = images.get(&logo.0) {
+        for (device, _) in streamdeck.devices() {
+            streamdeck.set_device_image_with_mode(
+                device,
+                image,
+                GridMode {
+                    image_mode: ImageMode {
+                        resize: ImageResize::AspectFill,
+                        ..default()
+                    },
+                    gap: 20,
+                },
+            );
+            *displayed = true;
+        }
+    }
+}
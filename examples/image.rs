@@ -10,7 +10,7 @@ fn main() {
             ImagePlugin::default(),
             LogPlugin::default(),
         ))
-        .add_plugins(StreamDeckPlugin)
+        .add_plugins(StreamDeckPlugin::default())
         .insert_resource(Time::<Fixed>::from_seconds(1.0))
         .add_systems(Startup, load_asset)
         .add_systems(FixedUpdate, change_image)
@@ -31,7 +31,7 @@ fn change_image(streamdeck: Res<StreamDeck>, logo: Res<Logo>, images: Res<Assets
         if let Some(kind) = streamdeck.kind() {
             let key = rng.random_range(0..kind.keys());
 
-            streamdeck.set_key_image(key, &image);
+            streamdeck.set_key_image(key, image);
             for i in 0..kind.keys() {
                 if i != key {
                     streamdeck.reset_key(i);
@@ -4,7 +4,7 @@ use bevy_streamdeck::{StreamDeckInput, StreamDeckKey, StreamDeckPlugin};
 fn main() {
     App::new()
         .add_plugins((MinimalPlugins, LogPlugin::default()))
-        .add_plugins(StreamDeckPlugin)
+        .add_plugins(StreamDeckPlugin::default())
         .add_systems(
             Update,
             (print_streamdeck_events, check_streamdeck_key_status),
@@ -21,10 +21,10 @@ fn print_streamdeck_events(mut streamdeck_input: MessageReader<StreamDeckInput>)
 fn check_streamdeck_key_status(streamdeck_key: Res<ButtonInput<StreamDeckKey>>) {
     for i in 0..50 {
         // TODO: check with the number of keys on the deck
-        if streamdeck_key.just_pressed(StreamDeckKey(i)) {
+        if streamdeck_key.just_pressed(StreamDeckKey::from(i)) {
             info!("key {} just pressed", i);
         }
-        if streamdeck_key.pressed(StreamDeckKey(i)) {
+        if streamdeck_key.pressed(StreamDeckKey::from(i)) {
             info!("key {} currently pressed", i);
         }
     }
@@ -0,0 +1,23 @@
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_streamdeck::{KeyColor, KeyPressed, StreamDeckPlugin};
+
+fn main() {
+    App::new()
+        .add_plugins((MinimalPlugins, LogPlugin::default()))
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Update, highlight_pressed_keys)
+        .run();
+}
+
+fn highlight_pressed_keys(
+    mut commands: Commands,
+    pressed: Query<Entity, Added<KeyPressed>>,
+    mut released: RemovedComponents<KeyPressed>,
+) {
+    for entity in &pressed {
+        commands.entity(entity).insert(KeyColor(Color::WHITE));
+    }
+    for entity in released.read() {
+        commands.entity(entity).remove::<KeyColor>();
+    }
+}
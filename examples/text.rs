@@ -0,0 +1,57 @@
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_streamdeck::{
+    FontArc, HorizontalAlign, StreamDeck, StreamDeckInput, StreamDeckKey, StreamDeckPlugin,
+    TextStyle, VerticalAlign,
+};
+
+// Run with `cargo run --example text --features text -- path/to/font.ttf`
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .expect("expected the path to a TrueType or OpenType font");
+    let font = FontArc::try_from_vec(std::fs::read(path).unwrap()).unwrap();
+
+    App::new()
+        .add_plugins((MinimalPlugins, LogPlugin::default()))
+        .add_plugins(StreamDeckPlugin::default())
+        .insert_resource(Counters {
+            style: TextStyle::new(font)
+                .with_size(20.0)
+                .with_alignment(HorizontalAlign::Center, VerticalAlign::Bottom),
+            presses: Default::default(),
+        })
+        .add_systems(Update, count_presses)
+        .run();
+}
+
+#[derive(Resource)]
+struct Counters {
+    style: TextStyle,
+    presses: std::collections::HashMap<StreamDeckKey, u32>,
+}
+
+fn count_presses(
+    streamdeck: Res<StreamDeck>,
+    mut counters: ResMut<Counters>,
+    mut streamdeck_input: MessageReader<StreamDeckInput>,
+) {
+    for event in streamdeck_input.read() {
+        match event {
+            StreamDeckInput::Connected(device, kind) => {
+                for key in 0..kind.keys() {
+                    let key = StreamDeckKey::new(*device, key);
+                    let count = counters.presses.get(&key).copied().unwrap_or_default();
+                    let text = format!("key {}\n{}", key.key, count);
+                    streamdeck.set_key_text(key, &text, &counters.style);
+                }
+            }
+            StreamDeckInput::Press(key) => {
+                let count = counters.presses.entry(*key).or_default();
+                *count += 1;
+                let text = format!("key {}\n{}", key.key, count);
+                streamdeck.set_key_text(*key, &text, &counters.style);
+            }
+            _ => (),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use bevy::{log::LogPlugin, prelude::*};
+use bevy_streamdeck::{
+    Kind, StreamDeck, StreamDeckInput, StreamDeckPlugin, VirtualKey, VirtualStreamDeck,
+};
+
+fn main() {
+    let deck = VirtualStreamDeck::new(Kind::Mini);
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        LogPlugin::default(),
+        StreamDeckPlugin::with_backend(deck.clone()),
+    ))
+    .add_systems(Update, light_pressed_keys);
+
+    deck.press(2);
+    wait_for(&mut app, || {
+        matches!(deck.key(2), VirtualKey::Color([255, 255, 255]))
+    });
+    deck.release(2);
+    wait_for(&mut app, || {
+        matches!(deck.key(2), VirtualKey::Color([0, 0, 0]))
+    });
+
+    info!("orders received: {:?}", deck.orders());
+}
+
+fn light_pressed_keys(streamdeck: Res<StreamDeck>, mut inputs: MessageReader<StreamDeckInput>) {
+    for input in inputs.read() {
+        match input {
+            StreamDeckInput::Press(key) => streamdeck.set_key_color(*key, Color::WHITE),
+            StreamDeckInput::Release(key) => streamdeck.reset_key(*key),
+            _ => (),
+        }
+    }
+}
+
+fn wait_for(app: &mut App, condition: impl Fn() -> bool) {
+    for _ in 0..1000 {
+        app.update();
+        if condition() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("the virtual Stream Deck did not reach the expected state");
+}
@@ -1,7 +1,7 @@
 use std::iter;
 
 use bevy::{app::AppExit, log::LogPlugin, prelude::*};
-use bevy_streamdeck::{Color, StreamDeck, StreamDeckKey, StreamDeckPlugin};
+use bevy_streamdeck::{Color, StreamDeck, StreamDeckInput, StreamDeckKey, StreamDeckPlugin};
 use rand::RngExt;
 
 // Lower to make it harder
@@ -10,8 +10,8 @@ const FACTOR: f64 = 1.0;
 fn main() {
     App::new()
         .add_plugins((MinimalPlugins, LogPlugin::default()))
-        .add_plugins(StreamDeckPlugin)
-        .add_systems(Startup, clean)
+        .add_plugins(StreamDeckPlugin::default())
+        .add_systems(Update, clean)
         .insert_resource(Time::<Fixed>::from_hz(1.0 / FACTOR))
         .add_systems(FixedUpdate, spawn_mole)
         .add_systems(PostUpdate, despawn_mole)
@@ -20,8 +20,13 @@ fn main() {
         .run();
 }
 
-fn clean(streamdeck: Res<StreamDeck>) {
-    streamdeck.reset();
+/// Orders are dropped until a device is connected, clean it once it is.
+fn clean(streamdeck: Res<StreamDeck>, mut inputs: MessageReader<StreamDeckInput>) {
+    for input in inputs.read() {
+        if let StreamDeckInput::Connected(device, _) = input {
+            streamdeck.reset_device(*device);
+        }
+    }
 }
 
 fn spawn_mole(
@@ -114,7 +119,7 @@ fn whack(
     mut app_exit: MessageWriter<AppExit>,
 ) {
     for (entity, mole) in moles.iter() {
-        if streamdeck_key.just_pressed(StreamDeckKey(mole.key)) {
+        if streamdeck_key.just_pressed(StreamDeckKey::from(mole.key)) {
             commands.entity(entity).despawn();
             streamdeck.reset_key(mole.key);
             match mole.ty {
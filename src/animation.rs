@@ -0,0 +1,317 @@
+use std::{io::Cursor, sync::Arc, time::Duration};
+
+use bevy_app::{App, PostUpdate};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{
+    component::Component,
+    lifecycle::RemovedComponents,
+    schedule::{IntoScheduleConfigs, common_conditions::resource_exists},
+    system::{Query, Res},
+};
+use bevy_image::{Image, TextureAtlasLayout};
+use bevy_time::Time;
+use image::{
+    AnimationDecoder, DynamicImage, ImageError,
+    codecs::{gif::GifDecoder, png::PngDecoder},
+};
+use log::warn;
+
+use crate::{
+    ImageConversionError, ImageMode, OrderError, StreamDeck, StreamDeckKeyIndex, image_to_dynamic,
+};
+
+/// What happens when the last frame of a [`KeyAnimation`] is reached.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AnimationMode {
+    /// Stop on the last frame.
+    Once,
+    /// Start again from the first frame.
+    #[default]
+    Repeat,
+    /// Play backward to the first frame, then forward again.
+    PingPong,
+}
+
+#[derive(Clone, Debug)]
+enum AnimationFrames {
+    Decoded(Arc<Vec<(DynamicImage, Duration)>>),
+    Images {
+        images: Vec<Handle<Image>>,
+        frame_duration: Duration,
+    },
+    Atlas {
+        image: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+        indices: Vec<usize>,
+        frame_duration: Duration,
+    },
+}
+
+/// Animation played on the key, driven by [`Time`].
+#[derive(Component, Clone, Debug)]
+pub struct KeyAnimation {
+    frames: AnimationFrames,
+    pub mode: AnimationMode,
+    pub image_mode: ImageMode,
+    paused: bool,
+    finished: bool,
+    forward: bool,
+    frame: usize,
+    elapsed: Duration,
+    displayed: Option<usize>,
+}
+
+impl KeyAnimation {
+    fn new(frames: AnimationFrames) -> Self {
+        Self {
+            frames,
+            mode: AnimationMode::default(),
+            image_mode: ImageMode::default(),
+            paused: false,
+            finished: false,
+            forward: true,
+            frame: 0,
+            elapsed: Duration::ZERO,
+            displayed: None,
+        }
+    }
+
+    /// Frames with how long each is displayed.
+    pub fn from_frames(frames: Vec<(DynamicImage, Duration)>) -> Self {
+        Self::new(AnimationFrames::Decoded(Arc::new(frames)))
+    }
+
+    /// Decode an animated GIF, with the frame timing from the file.
+    pub fn from_gif(bytes: &[u8]) -> Result<Self, ImageError> {
+        let frames = GifDecoder::new(Cursor::new(bytes))?
+            .into_frames()
+            .collect_frames()?;
+        Ok(Self::from_decoded(frames))
+    }
+
+    /// Decode an animated PNG, with the frame timing from the file.
+    pub fn from_apng(bytes: &[u8]) -> Result<Self, ImageError> {
+        let frames = PngDecoder::new(Cursor::new(bytes))?
+            .apng()?
+            .into_frames()
+            .collect_frames()?;
+        Ok(Self::from_decoded(frames))
+    }
+
+    fn from_decoded(frames: Vec<image::Frame>) -> Self {
+        Self::from_frames(
+            frames
+                .into_iter()
+                .map(|frame| {
+                    let (numerator, denominator) = frame.delay().numer_denom_ms();
+                    let mut delay =
+                        Duration::from_secs_f64(numerator as f64 / denominator as f64 / 1000.0);
+                    // Like browsers, treat frames without a delay as 100ms
+                    if delay.is_zero() {
+                        delay = Duration::from_millis(100);
+                    }
+                    (DynamicImage::ImageRgba8(frame.into_buffer()), delay)
+                })
+                .collect(),
+        )
+    }
+
+    /// One image per frame, each displayed for `frame_duration`.
+    pub fn from_images(
+        images: impl IntoIterator<Item = Handle<Image>>,
+        frame_duration: Duration,
+    ) -> Self {
+        Self::new(AnimationFrames::Images {
+            images: images.into_iter().collect(),
+            frame_duration,
+        })
+    }
+
+    /// Frames from a sprite sheet, in the order of `indices`, each displayed for `frame_duration`.
+    pub fn from_atlas(
+        image: Handle<Image>,
+        layout: Handle<TextureAtlasLayout>,
+        indices: impl IntoIterator<Item = usize>,
+        frame_duration: Duration,
+    ) -> Self {
+        Self::new(AnimationFrames::Atlas {
+            image,
+            layout,
+            indices: indices.into_iter().collect(),
+            frame_duration,
+        })
+    }
+
+    pub fn with_mode(mut self, mode: AnimationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_image_mode(mut self, image_mode: ImageMode) -> Self {
+        self.image_mode = image_mode;
+        self
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether an [`AnimationMode::Once`] animation reached its last frame.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Play again from the first frame.
+    pub fn restart(&mut self) {
+        self.finished = false;
+        self.forward = true;
+        self.frame = 0;
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// Index of the frame currently displayed.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    fn len(&self) -> usize {
+        match &self.frames {
+            AnimationFrames::Decoded(frames) => frames.len(),
+            AnimationFrames::Images { images, .. } => images.len(),
+            AnimationFrames::Atlas { indices, .. } => indices.len(),
+        }
+    }
+
+    fn frame_duration(&self) -> Duration {
+        match &self.frames {
+            AnimationFrames::Decoded(frames) => frames[self.frame].1,
+            AnimationFrames::Images { frame_duration, .. }
+            | AnimationFrames::Atlas { frame_duration, .. } => *frame_duration,
+        }
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        if self.paused || self.finished || self.len() == 0 {
+            return;
+        }
+        self.elapsed += delta;
+        while !self.finished
+            && !self.frame_duration().is_zero()
+            && self.elapsed >= self.frame_duration()
+        {
+            self.elapsed -= self.frame_duration();
+            self.step();
+        }
+    }
+
+    fn step(&mut self) {
+        let last = self.len() - 1;
+        match self.mode {
+            AnimationMode::Once if self.frame == last => self.finished = true,
+            AnimationMode::Once => self.frame += 1,
+            AnimationMode::Repeat => self.frame = (self.frame + 1) % self.len(),
+            AnimationMode::PingPong if last == 0 => (),
+            AnimationMode::PingPong => {
+                if self.frame == last {
+                    self.forward = false;
+                } else if self.frame == 0 {
+                    self.forward = true;
+                }
+                if self.forward {
+                    self.frame += 1;
+                } else {
+                    self.frame -= 1;
+                }
+            }
+        }
+    }
+
+    /// The current frame, or `None` while its image is loading.
+    fn frame_image(
+        &self,
+        images: &Assets<Image>,
+        layouts: &Assets<TextureAtlasLayout>,
+    ) -> Option<Result<DynamicImage, ImageConversionError>> {
+        match &self.frames {
+            AnimationFrames::Decoded(frames) => Some(Ok(frames[self.frame].0.clone())),
+            AnimationFrames::Images {
+                images: handles, ..
+            } => images.get(&handles[self.frame]).map(image_to_dynamic),
+            AnimationFrames::Atlas {
+                image,
+                layout,
+                indices,
+                ..
+            } => {
+                let rect = *layouts.get(layout)?.textures.get(indices[self.frame])?;
+                Some(images.get(image).map(image_to_dynamic)?.map(|image| {
+                    image.crop_imm(rect.min.x, rect.min.y, rect.width(), rect.height())
+                }))
+            }
+        }
+    }
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(
+        PostUpdate,
+        (
+            play_key_animations
+                .run_if(resource_exists::<Assets<Image>>)
+                .run_if(resource_exists::<Assets<TextureAtlasLayout>>),
+            reset_removed_animations,
+        ),
+    );
+}
+
+/// Advance animations, and send their frame when it changed. Frames that are loading or whose
+/// device is not connected yet are retried each frame.
+fn play_key_animations(
+    time: Res<Time>,
+    streamdeck: Res<StreamDeck>,
+    images: Res<Assets<Image>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut animations: Query<(&StreamDeckKeyIndex, &mut KeyAnimation)>,
+) {
+    for (index, mut animation) in &mut animations {
+        animation.tick(time.delta());
+        if animation.len() == 0 || animation.displayed == Some(animation.frame) {
+            continue;
+        }
+        let Some(image) = animation.frame_image(&images, &layouts) else {
+            continue;
+        };
+        let result = image.map_err(OrderError::from).and_then(|image| {
+            streamdeck.set_key_dynamic_image(index.0, image, &animation.image_mode)
+        });
+        match result {
+            Err(OrderError::NotConnected(_)) => continue,
+            Err(OrderError::UnsupportedImage(error)) => {
+                warn!("Can't display animation on key {}: {}", index.0.key, error);
+            }
+            _ => (),
+        }
+        animation.displayed = Some(animation.frame);
+    }
+}
+
+/// Clear keys that no longer have an animation.
+fn reset_removed_animations(
+    streamdeck: Res<StreamDeck>,
+    mut removed: RemovedComponents<KeyAnimation>,
+    keys: Query<&StreamDeckKeyIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(index) = keys.get(entity) {
+            streamdeck.reset_key(index.0);
+        }
+    }
+}
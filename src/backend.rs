@@ -0,0 +1,501 @@
+use std::time::Duration;
+
+use bevy_math::UVec2;
+#[cfg(feature = "images")]
+use hidapi::HidError;
+use hidapi::{HidApi, HidDevice};
+#[cfg(feature = "images")]
+use image::{DynamicImage, codecs::jpeg::JpegEncoder};
+use log::warn;
+use streamdeck::{Colour, Error};
+
+use crate::{Kind, TouchInput, touchstrip_size};
+
+/// Vendor id of Elgato devices.
+pub const ELGATO_VENDOR_ID: u16 = 0x0fd9;
+
+/// Which devices the plugin can connect to. By default, any supported device is accepted.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceFilter {
+    /// Only accept these kinds of device.
+    pub kinds: Option<Vec<Kind>>,
+    /// Only accept devices with these product ids.
+    pub product_ids: Option<Vec<u16>>,
+    /// Only accept devices with these serials. When several are available, they are connected in
+    /// this order.
+    pub serials: Option<Vec<String>>,
+}
+
+impl DeviceFilter {
+    pub fn kinds(kinds: impl IntoIterator<Item = Kind>) -> Self {
+        Self {
+            kinds: Some(kinds.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    pub fn product_ids(product_ids: impl IntoIterator<Item = u16>) -> Self {
+        Self {
+            product_ids: Some(product_ids.into_iter().collect()),
+            ..Default::default()
+        }
+    }
+
+    pub fn serials(serials: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            serials: Some(serials.into_iter().map(Into::into).collect()),
+            ..Default::default()
+        }
+    }
+
+    pub fn accepts_kind(&self, kind: Kind) -> bool {
+        self.kinds
+            .as_ref()
+            .is_none_or(|kinds| kinds.contains(&kind))
+    }
+
+    pub fn accepts_product_id(&self, product_id: u16) -> bool {
+        self.product_ids
+            .as_ref()
+            .is_none_or(|product_ids| product_ids.contains(&product_id))
+    }
+
+    pub fn accepts_serial(&self, serial: &str) -> bool {
+        self.serial_priority(serial).is_some()
+    }
+
+    /// Position of the serial in the preference list, lower is preferred.
+    pub fn serial_priority(&self, serial: &str) -> Option<usize> {
+        match self.serials.as_ref() {
+            Some(serials) => serials.iter().position(|preferred| preferred == serial),
+            None => Some(0),
+        }
+    }
+}
+
+/// A source of Stream Deck connections, driven by the IO task of the plugin.
+pub trait StreamDeckBackend: Send + 'static {
+    /// Try to connect to a device accepted by the filter.
+    ///
+    /// `connected` lists the serials of the devices already connected, which must be skipped.
+    fn connect(
+        &mut self,
+        filter: &DeviceFilter,
+        connected: &[String],
+    ) -> Result<Box<dyn StreamDeckConnection>, Error>;
+}
+
+/// Input read from a device.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceInput {
+    /// State of each key, 1 when pressed.
+    Buttons(Vec<u8>),
+    /// State of each encoder button, 1 when pressed.
+    EncoderButtons(Vec<u8>),
+    /// Ticks each encoder turned, positive clockwise.
+    EncoderTwist(Vec<i8>),
+    Touch(TouchInput),
+}
+
+/// Whether this kind of device has a display. Devices without one, like the Pedal, are only used
+/// for input.
+pub fn has_display(kind: Kind) -> bool {
+    !matches!(kind, Kind::Pedal)
+}
+
+/// Number of encoders on this kind of device.
+pub fn encoders(kind: Kind) -> u8 {
+    match kind {
+        Kind::Plus => 4,
+        _ => 0,
+    }
+}
+
+/// A connected Stream Deck device.
+pub trait StreamDeckConnection: Send {
+    fn kind(&self) -> Kind;
+
+    /// Identifies the device, so that it can be recognised when reconnected.
+    fn serial(&self) -> String;
+
+    fn firmware_version(&mut self) -> Result<String, Error> {
+        Err(Error::NoData)
+    }
+
+    /// Read the state of each key, returning [`Error::NoData`] if nothing was read before the timeout.
+    fn read_buttons(&mut self, timeout: Option<Duration>) -> Result<Vec<u8>, Error>;
+
+    /// Read any input, returning [`Error::NoData`] if nothing was read before the timeout. Devices
+    /// with other inputs than keys need to implement it, by default only keys are read.
+    fn read_input(&mut self, timeout: Option<Duration>) -> Result<DeviceInput, Error> {
+        self.read_buttons(timeout).map(DeviceInput::Buttons)
+    }
+
+    /// Set a key to a solid color. `key` starts at 1.
+    fn set_button_rgb(&mut self, key: u8, colour: &Colour) -> Result<(), Error>;
+
+    /// Set a key to an image. `key` starts at 1.
+    #[cfg(feature = "images")]
+    fn set_button_image(&mut self, key: u8, image: DynamicImage) -> Result<(), Error>;
+
+    fn reset(&mut self) -> Result<(), Error>;
+
+    /// Set the brightness of the screen, from 0 to 100. Devices without a screen can ignore it.
+    fn set_brightness(&mut self, _percent: u8) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Whether images can be displayed on the touch strip. By default, any device with a touch
+    /// strip can.
+    fn has_touchstrip(&self) -> bool {
+        touchstrip_size(self.kind()).is_some()
+    }
+
+    /// Display an image on the touch strip, with its top left corner at `position`. Devices
+    /// without a touch strip can ignore it.
+    #[cfg(feature = "images")]
+    fn set_touchstrip_image(
+        &mut self,
+        _position: UVec2,
+        _image: DynamicImage,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Default backend, using `hidapi` to talk to the device.
+///
+/// Every Elgato device whose [`Kind`] is known is tried, others are ignored. The Pedal, which
+/// `streamdeck` doesn't support, is read from its raw input reports.
+#[derive(Default)]
+pub struct HidBackend;
+
+impl StreamDeckBackend for HidBackend {
+    fn connect(
+        &mut self,
+        filter: &DeviceFilter,
+        connected: &[String],
+    ) -> Result<Box<dyn StreamDeckConnection>, Error> {
+        let api = HidApi::new().map_err(Error::Hid)?;
+        let mut candidates = api
+            .device_list()
+            .filter(|device| device.vendor_id() == ELGATO_VENDOR_ID)
+            .filter(|device| filter.accepts_product_id(device.product_id()))
+            .filter_map(|device| {
+                // Unknown devices, like the Neo, are skipped without reporting an error
+                let kind = Kind::from_product_id(device.product_id())?;
+                if !filter.accepts_kind(kind) {
+                    return None;
+                }
+                // Devices without a serial are identified by their path
+                let serial = device
+                    .serial_number()
+                    .map(String::from)
+                    .unwrap_or_else(|| device.path().to_string_lossy().into_owned());
+                let priority = filter.serial_priority(&serial)?;
+                (!connected.contains(&serial)).then_some((priority, serial, kind, device))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(priority, ..)| *priority);
+
+        let mut last_error = Error::NoData;
+        for (_, serial, kind, device) in candidates {
+            if kind == Kind::Pedal {
+                match api.open_path(device.path()) {
+                    Ok(input) => {
+                        return Ok(Box::new(HidConnection {
+                            kind: Kind::Pedal,
+                            streamdeck: None,
+                            input: Some(input),
+                            serial,
+                        }));
+                    }
+                    Err(error) => {
+                        last_error = Error::Hid(error);
+                        continue;
+                    }
+                }
+            }
+            match streamdeck::StreamDeck::connect_with_hid(
+                &api,
+                device.vendor_id(),
+                device.product_id(),
+                device.serial_number().map(String::from),
+            ) {
+                Ok(streamdeck) => {
+                    // `streamdeck` only reads keys, other inputs are read from the raw reports.
+                    // Opening the device a second time fails where hidapi opens it exclusively,
+                    // like on macOS without its `macos-shared-device` feature.
+                    let input = if encoders(kind) > 0 {
+                        api.open_path(device.path())
+                            .inspect_err(|error| {
+                                warn!(
+                                    "Can't read encoders and touch strip of StreamDeck {}, only keys will be read: {}",
+                                    serial, error
+                                )
+                            })
+                            .ok()
+                    } else {
+                        None
+                    };
+                    return Ok(Box::new(HidConnection {
+                        kind,
+                        streamdeck: Some(streamdeck),
+                        input,
+                        serial,
+                    }));
+                }
+                Err(error) => last_error = error,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+struct HidConnection {
+    kind: Kind,
+    // None for devices not supported by `streamdeck`, which only have `input`
+    streamdeck: Option<streamdeck::StreamDeck>,
+    // Second handle, reading the raw input reports
+    input: Option<HidDevice>,
+    serial: String,
+}
+
+impl HidConnection {
+    fn streamdeck(&mut self) -> Result<&mut streamdeck::StreamDeck, Error> {
+        self.streamdeck.as_mut().ok_or(Error::UnsupportedCommand)
+    }
+
+    fn read_report(&self, timeout: Option<Duration>) -> Result<DeviceInput, Error> {
+        let input = self.input.as_ref().ok_or(Error::NoData)?;
+        let mut report = [0; 64];
+        let read = input
+            .read_timeout(
+                &mut report,
+                timeout.map_or(-1, |timeout| timeout.as_millis() as i32),
+            )
+            .map_err(Error::Hid)?;
+        parse_input_report(&report[..read], self.kind.keys()).ok_or(Error::NoData)
+    }
+}
+
+impl StreamDeckConnection for HidConnection {
+    fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    fn serial(&self) -> String {
+        self.serial.clone()
+    }
+
+    fn firmware_version(&mut self) -> Result<String, Error> {
+        self.streamdeck()?.version()
+    }
+
+    fn read_buttons(&mut self, timeout: Option<Duration>) -> Result<Vec<u8>, Error> {
+        match &mut self.streamdeck {
+            Some(streamdeck) => streamdeck.read_buttons(timeout),
+            None => match self.read_report(timeout)? {
+                DeviceInput::Buttons(buttons) => Ok(buttons),
+                _ => Err(Error::NoData),
+            },
+        }
+    }
+
+    fn read_input(&mut self, timeout: Option<Duration>) -> Result<DeviceInput, Error> {
+        if self.input.is_none() {
+            return self.read_buttons(timeout).map(DeviceInput::Buttons);
+        }
+        self.read_report(timeout)
+    }
+
+    fn set_button_rgb(&mut self, key: u8, colour: &Colour) -> Result<(), Error> {
+        self.streamdeck()?.set_button_rgb(key, colour)
+    }
+
+    #[cfg(feature = "images")]
+    fn set_button_image(&mut self, key: u8, image: DynamicImage) -> Result<(), Error> {
+        self.streamdeck()?.set_button_image(key, image)
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.streamdeck()?.reset()
+    }
+
+    fn set_brightness(&mut self, percent: u8) -> Result<(), Error> {
+        self.streamdeck()?.set_brightness(percent)
+    }
+
+    fn has_touchstrip(&self) -> bool {
+        self.input.is_some() && touchstrip_size(self.kind).is_some()
+    }
+
+    #[cfg(feature = "images")]
+    fn set_touchstrip_image(&mut self, position: UVec2, image: DynamicImage) -> Result<(), Error> {
+        match &self.input {
+            Some(device) => write_touchstrip_image(device, position, &image),
+            None => Err(Error::UnsupportedCommand),
+        }
+    }
+}
+
+/// Parse an input report of a Stream Deck+ or Pedal, with `keys` keys.
+///
+/// The second byte is the type of input: 0 for keys, with their state from the fifth byte, 2 for the
+/// touch strip, with the kind of touch in the fifth byte followed by coordinates, or 3 for encoders,
+/// with 0 for buttons or 1 for rotation in the fifth byte followed by a value per encoder.
+fn parse_input_report(report: &[u8], keys: u8) -> Option<DeviceInput> {
+    let coordinate = |i: usize| Some(u16::from_le_bytes([*report.get(i)?, *report.get(i + 1)?]));
+    match report.get(1)? {
+        0x00 => Some(DeviceInput::Buttons(
+            report.get(4..4 + keys as usize)?.to_vec(),
+        )),
+        0x02 => {
+            let position = UVec2::new(coordinate(6)? as u32, coordinate(8)? as u32);
+            let touch = match report.get(4)? {
+                0x01 => TouchInput::Tap(position),
+                0x02 => TouchInput::LongPress(position),
+                0x03 => TouchInput::Swipe {
+                    start: position,
+                    end: UVec2::new(coordinate(10)? as u32, coordinate(12)? as u32),
+                },
+                _ => return None,
+            };
+            Some(DeviceInput::Touch(touch))
+        }
+        0x03 => {
+            let values = report.get(5..9)?;
+            match report.get(4)? {
+                0x00 => Some(DeviceInput::EncoderButtons(values.to_vec())),
+                0x01 => Some(DeviceInput::EncoderTwist(
+                    values.iter().map(|value| *value as i8).collect(),
+                )),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Send a JPEG image to the touch strip of a Stream Deck+, split in 1024 bytes reports.
+///
+/// Each report starts with a 16 bytes header: the report id and command, the position and size of
+/// the image, whether it is the last report, the index of the report and the length of its data.
+#[cfg(feature = "images")]
+fn write_touchstrip_image(
+    device: &HidDevice,
+    position: UVec2,
+    image: &DynamicImage,
+) -> Result<(), Error> {
+    const REPORT_SIZE: usize = 1024;
+    const HEADER_SIZE: usize = 16;
+
+    let mut jpeg = Vec::new();
+    image
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, 90))
+        .map_err(|error| {
+            Error::Hid(HidError::HidApiError {
+                message: error.to_string(),
+            })
+        })?;
+
+    let chunks = jpeg.chunks(REPORT_SIZE - HEADER_SIZE);
+    let count = chunks.len();
+    for (page, chunk) in chunks.enumerate() {
+        let mut report = [0; REPORT_SIZE];
+        report[0] = 0x02;
+        report[1] = 0x0c;
+        report[2..4].copy_from_slice(&(position.x as u16).to_le_bytes());
+        report[4..6].copy_from_slice(&(position.y as u16).to_le_bytes());
+        report[6..8].copy_from_slice(&(image.width() as u16).to_le_bytes());
+        report[8..10].copy_from_slice(&(image.height() as u16).to_le_bytes());
+        report[10] = (page + 1 == count) as u8;
+        report[11..13].copy_from_slice(&(page as u16).to_le_bytes());
+        report[13..15].copy_from_slice(&(chunk.len() as u16).to_le_bytes());
+        report[HEADER_SIZE..HEADER_SIZE + chunk.len()].copy_from_slice(chunk);
+        device.write(&report).map_err(Error::Hid)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut report = vec![0x01, kind, 0x00, 0x00];
+        report.extend_from_slice(payload);
+        report.resize(64, 0);
+        report
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(
+            parse_input_report(&report(0x00, &[0, 1, 0, 0, 0, 0, 0, 1]), 8),
+            Some(DeviceInput::Buttons(vec![0, 1, 0, 0, 0, 0, 0, 1]))
+        );
+    }
+
+    #[test]
+    fn parses_pedal_keys() {
+        assert_eq!(
+            parse_input_report(&report(0x00, &[0, 0, 1]), 3),
+            Some(DeviceInput::Buttons(vec![0, 0, 1]))
+        );
+    }
+
+    #[test]
+    fn parses_encoders() {
+        assert_eq!(
+            parse_input_report(&report(0x03, &[0x00, 1, 0, 0, 1]), 8),
+            Some(DeviceInput::EncoderButtons(vec![1, 0, 0, 1]))
+        );
+        assert_eq!(
+            parse_input_report(&report(0x03, &[0x01, 2, 0xff, 0, 0xfe]), 8),
+            Some(DeviceInput::EncoderTwist(vec![2, -1, 0, -2]))
+        );
+    }
+
+    #[test]
+    fn parses_touches() {
+        let position = |x: u16, y: u16| [x.to_le_bytes(), y.to_le_bytes()].concat();
+        let touch = |kind: u8, coordinates: &[u8]| {
+            let mut payload = vec![kind, 0x00];
+            payload.extend_from_slice(coordinates);
+            parse_input_report(&report(0x02, &payload), 8)
+        };
+
+        assert_eq!(
+            touch(0x01, &position(300, 50)),
+            Some(DeviceInput::Touch(TouchInput::Tap(UVec2::new(300, 50))))
+        );
+        assert_eq!(
+            touch(0x02, &position(799, 99)),
+            Some(DeviceInput::Touch(TouchInput::LongPress(UVec2::new(
+                799, 99
+            ))))
+        );
+        assert_eq!(
+            touch(0x03, &[position(100, 20), position(600, 80)].concat()),
+            Some(DeviceInput::Touch(TouchInput::Swipe {
+                start: UVec2::new(100, 20),
+                end: UVec2::new(600, 80),
+            }))
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_and_truncated_reports() {
+        assert_eq!(parse_input_report(&report(0x05, &[]), 8), None);
+        assert_eq!(parse_input_report(&report(0x03, &[0x02]), 8), None);
+        assert_eq!(parse_input_report(&report(0x02, &[0x07]), 8), None);
+        assert_eq!(parse_input_report(&[0x01, 0x00, 0x00, 0x00, 1, 0], 8), None);
+        assert_eq!(
+            parse_input_report(&[0x01, 0x02, 0x00, 0x00, 0x03, 0x00, 1, 0, 1, 0], 8),
+            None
+        );
+        assert_eq!(parse_input_report(&[], 8), None);
+    }
+}
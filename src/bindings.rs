@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use bevy_app::{App, PostUpdate};
+use bevy_asset::{AssetEvent, AssetId, Assets, Handle};
+use bevy_ecs::{
+    message::MessageReader,
+    resource::Resource,
+    schedule::{IntoScheduleConfigs, common_conditions::resource_exists},
+    system::{Res, ResMut},
+};
+use bevy_image::Image;
+
+use crate::{ImageMode, OrderError, StreamDeck, StreamDeckKey};
+
+/// Images bound to keys. A bound image is sent once loaded, and sent again each time it is modified.
+#[derive(Resource, Default)]
+pub struct KeyImageBindings {
+    bindings: HashMap<StreamDeckKey, Binding>,
+}
+
+struct Binding {
+    handle: Handle<Image>,
+    mode: ImageMode,
+    pending: bool,
+}
+
+impl KeyImageBindings {
+    pub fn bind(&mut self, key: impl Into<StreamDeckKey>, handle: Handle<Image>) {
+        self.bind_with_mode(key, handle, ImageMode::default());
+    }
+
+    pub fn bind_with_mode(
+        &mut self,
+        key: impl Into<StreamDeckKey>,
+        handle: Handle<Image>,
+        mode: ImageMode,
+    ) {
+        self.bindings.insert(
+            key.into(),
+            Binding {
+                handle,
+                mode,
+                pending: true,
+            },
+        );
+    }
+
+    /// Stop following the image bound to the key. The key keeps displaying it.
+    pub fn unbind(&mut self, key: impl Into<StreamDeckKey>) -> Option<Handle<Image>> {
+        self.bindings
+            .remove(&key.into())
+            .map(|binding| binding.handle)
+    }
+
+    pub fn get(&self, key: impl Into<StreamDeckKey>) -> Option<&Handle<Image>> {
+        self.bindings
+            .get(&key.into())
+            .map(|binding| &binding.handle)
+    }
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.init_resource::<KeyImageBindings>().add_systems(
+        PostUpdate,
+        upload_bound_images.run_if(resource_exists::<Assets<Image>>),
+    );
+}
+
+/// Whether the image was loaded or modified since the last frame.
+pub(crate) fn image_changed(event: &AssetEvent<Image>, id: AssetId<Image>) -> bool {
+    match event {
+        AssetEvent::LoadedWithDependencies { id: changed }
+        | AssetEvent::Modified { id: changed } => *changed == id,
+        _ => false,
+    }
+}
+
+fn upload_bound_images(
+    streamdeck: Res<StreamDeck>,
+    images: Res<Assets<Image>>,
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    mut bindings: ResMut<KeyImageBindings>,
+) {
+    for event in image_events.read() {
+        for binding in bindings.bindings.values_mut() {
+            if image_changed(event, binding.handle.id()) {
+                binding.pending = true;
+            }
+        }
+    }
+
+    for (key, binding) in bindings.bindings.iter_mut().filter(|(_, b)| b.pending) {
+        let Some(image) = images.get(&binding.handle) else {
+            continue;
+        };
+        // Keep trying until the device is connected
+        binding.pending = matches!(
+            streamdeck.try_set_key_image_with_mode(*key, image, binding.mode),
+            Err(OrderError::NotConnected(_))
+        );
+    }
+}
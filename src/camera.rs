@@ -0,0 +1,199 @@
+use std::time::{Duration, Instant};
+
+use bevy_app::{App, PostUpdate};
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    observer::On,
+    query::Has,
+    system::{Commands, Query, Res},
+};
+use bevy_image::{Image, TextureFormatPixelInfo};
+use bevy_render::{
+    gpu_readback::{Readback, ReadbackComplete},
+    renderer::RenderDevice,
+};
+
+use crate::{DeviceId, GridMode, StreamDeck, StreamDeckKey};
+
+/// Where a [`RenderToDeck`] image is displayed.
+#[derive(Clone, Copy, Debug)]
+pub enum DeckTarget {
+    Key(StreamDeckKey),
+    /// Across all keys of a device.
+    Device(DeviceId, GridMode),
+}
+
+/// Display an image rendered on the GPU, usually the target of a camera, on the deck.
+///
+/// The image is read back from the GPU at most once per `interval`.
+#[derive(Component, Clone, Debug)]
+pub struct RenderToDeck {
+    pub image: Handle<Image>,
+    pub target: DeckTarget,
+    pub interval: Duration,
+    last_readback: Option<Instant>,
+}
+
+impl RenderToDeck {
+    /// Display the image on a key, 10 times per second.
+    pub fn key(image: Handle<Image>, key: impl Into<StreamDeckKey>) -> Self {
+        Self {
+            image,
+            target: DeckTarget::Key(key.into()),
+            interval: Duration::from_millis(100),
+            last_readback: None,
+        }
+    }
+
+    /// Display the image across all keys of a device, 10 times per second.
+    pub fn device(image: Handle<Image>, device: DeviceId, grid_mode: GridMode) -> Self {
+        Self {
+            image,
+            target: DeckTarget::Device(device, grid_mode),
+            interval: Duration::from_millis(100),
+            last_readback: None,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(PostUpdate, request_readbacks)
+        .add_observer(display_readback);
+}
+
+/// Read the image back only on frames where it is due, instead of every frame.
+fn request_readbacks(
+    mut commands: Commands,
+    mut targets: Query<(Entity, &mut RenderToDeck, Has<Readback>)>,
+) {
+    let now = Instant::now();
+    for (entity, mut target, has_readback) in &mut targets {
+        if target
+            .last_readback
+            .is_none_or(|last| now - last >= target.interval)
+        {
+            target.last_readback = Some(now);
+            commands
+                .entity(entity)
+                .insert(Readback::texture(target.image.clone()));
+        } else if has_readback {
+            commands.entity(entity).remove::<Readback>();
+        }
+    }
+}
+
+fn display_readback(
+    readback: On<ReadbackComplete>,
+    streamdeck: Res<StreamDeck>,
+    images: Res<Assets<Image>>,
+    targets: Query<&RenderToDeck>,
+) {
+    let Ok(target) = targets.get(readback.entity) else {
+        return;
+    };
+    let Some(image) = images.get(&target.image) else {
+        return;
+    };
+    // The render target only has its data on the GPU
+    let mut frame = image.clone();
+    frame.data = Some(strip_row_padding(&readback.data, image));
+    match target.target {
+        DeckTarget::Key(key) => streamdeck.set_key_image(key, &frame),
+        DeckTarget::Device(device, grid_mode) => {
+            streamdeck.set_device_image_with_mode(device, &frame, grid_mode)
+        }
+    }
+}
+
+/// Remove the padding at the end of each row, as rows copied from the GPU are aligned to 256 bytes.
+fn strip_row_padding(data: &[u8], image: &Image) -> Vec<u8> {
+    let Ok(pixel_size) = image.texture_descriptor.format.pixel_size() else {
+        return data.to_vec();
+    };
+    let row = image.width() as usize * pixel_size;
+    let padded_row = RenderDevice::align_copy_bytes_per_row(row);
+    if row == padded_row {
+        return data.to_vec();
+    }
+    data.chunks(padded_row)
+        .flat_map(|padded| &padded[..row.min(padded.len())])
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::RenderAssetUsages,
+        prelude::*,
+        render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    };
+
+    use super::*;
+    use crate::{Kind, StreamDeckPlugin, VirtualKey, VirtualStreamDeck};
+
+    #[test]
+    fn displays_padded_readbacks() {
+        let deck = VirtualStreamDeck::new(Kind::Mini);
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            StreamDeckPlugin::with_backend(deck.clone()),
+        ))
+        .init_asset::<Image>();
+        let size = Extent3d {
+            width: 3,
+            height: 2,
+            depth_or_array_layers: 1,
+        };
+        let image = Image::new_uninit(
+            size,
+            TextureDimension::D2,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        let image = app.world_mut().resource_mut::<Assets<Image>>().add(image);
+        let entity = app.world_mut().spawn(RenderToDeck::key(image, 0)).id();
+
+        // A red row and a blue row, each padded to 256 bytes with white
+        let mut data = vec![255; 512];
+        for x in 0..3 {
+            data[x * 4..x * 4 + 4].copy_from_slice(&[255, 0, 0, 255]);
+            data[256 + x * 4..256 + x * 4 + 4].copy_from_slice(&[0, 0, 255, 255]);
+        }
+        for _ in 0..1000 {
+            app.update();
+            if app.world().resource::<StreamDeck>().kind().is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        app.world_mut().trigger(ReadbackComplete { entity, data });
+        for _ in 0..1000 {
+            app.update();
+            if matches!(deck.key(0), VirtualKey::Image(_)) {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let VirtualKey::Image(displayed) = deck.key(0) else {
+            panic!("the readback was not displayed");
+        };
+        let displayed = displayed.to_rgb8();
+        let (top, bottom) = (displayed.get_pixel(40, 0).0, displayed.get_pixel(40, 79).0);
+        assert!(top[0] > 200 && top[1] < 50 && top[2] < 50, "{top:?}");
+        assert!(
+            bottom[0] < 50 && bottom[1] < 50 && bottom[2] > 200,
+            "{bottom:?}"
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex},
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, Sender, bounded};
+
+use crate::StreamDeckOrder;
+
+/// What to do when a channel between the ECS and the IO task is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait until there is room in the channel. Systems never wait for the order queue: the
+    /// order is discarded instead, and its `try_` method fails with
+    /// [`OrderError::QueueFull`](crate::OrderError::QueueFull).
+    #[default]
+    Block,
+    /// Keep every message, growing the channel past its capacity.
+    Grow,
+    /// Discard the message being sent.
+    DropNewest,
+    /// Discard the oldest message in the channel to make room.
+    DropOldest,
+}
+
+/// Sending half of a bounded channel, applying an [`OverflowPolicy`] when it is full.
+pub(crate) struct PolicySender<T> {
+    shared: Arc<PolicyShared<T>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+/// Receiving half of a bounded channel. Dropping it makes sending fail.
+pub(crate) struct PolicyReceiver<T> {
+    shared: Arc<PolicyShared<T>>,
+}
+
+struct PolicyShared<T> {
+    pending: Mutex<PendingMessages<T>>,
+    not_full: Condvar,
+}
+
+struct PendingMessages<T> {
+    // Messages with whether the overflow policy applies to them
+    messages: VecDeque<(T, bool)>,
+    bounded: usize,
+    closed: bool,
+}
+
+pub(crate) fn channel<T>(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (PolicySender<T>, PolicyReceiver<T>) {
+    let shared = Arc::new(PolicyShared {
+        pending: Mutex::new(PendingMessages {
+            messages: VecDeque::new(),
+            bounded: 0,
+            closed: false,
+        }),
+        not_full: Condvar::new(),
+    });
+    (
+        PolicySender {
+            shared: shared.clone(),
+            capacity: capacity.max(1),
+            policy,
+        },
+        PolicyReceiver { shared },
+    )
+}
+
+impl<T> PolicySender<T> {
+    /// Send a message, returning it if it was discarded.
+    pub(crate) fn send(&self, message: T) -> Result<(), T> {
+        let mut pending = self.shared.pending.lock().unwrap();
+        while pending.bounded >= self.capacity && !pending.closed {
+            match self.policy {
+                OverflowPolicy::Block => {
+                    pending = self.shared.not_full.wait(pending).unwrap();
+                }
+                OverflowPolicy::Grow => break,
+                OverflowPolicy::DropNewest => return Err(message),
+                OverflowPolicy::DropOldest => {
+                    let oldest = pending.messages.iter().position(|(_, bounded)| *bounded);
+                    if let Some(oldest) = oldest {
+                        pending.messages.remove(oldest);
+                        pending.bounded -= 1;
+                    }
+                }
+            }
+        }
+        if pending.closed {
+            return Err(message);
+        }
+        pending.messages.push_back((message, true));
+        pending.bounded += 1;
+        Ok(())
+    }
+
+    /// Send a message that is never discarded and doesn't count towards the capacity, returning
+    /// it if the receiver was dropped.
+    pub(crate) fn send_unbounded(&self, message: T) -> Result<(), T> {
+        let mut pending = self.shared.pending.lock().unwrap();
+        if pending.closed {
+            return Err(message);
+        }
+        pending.messages.push_back((message, false));
+        Ok(())
+    }
+
+    /// Whether the receiver was dropped.
+    pub(crate) fn is_closed(&self) -> bool {
+        self.shared.pending.lock().unwrap().closed
+    }
+}
+
+impl<T> PolicyReceiver<T> {
+    /// Take all pending messages, in the order they were sent.
+    pub(crate) fn try_iter(&self) -> impl Iterator<Item = T> + use<T> {
+        let mut pending = self.shared.pending.lock().unwrap();
+        let messages = std::mem::take(&mut pending.messages);
+        pending.bounded = 0;
+        self.shared.not_full.notify_all();
+        messages.into_iter().map(|(message, _)| message)
+    }
+}
+
+impl<T> Drop for PolicyReceiver<T> {
+    fn drop(&mut self) {
+        let mut pending = self.shared.pending.lock().unwrap();
+        pending.closed = true;
+        pending.messages.clear();
+        self.shared.not_full.notify_all();
+    }
+}
+
+/// Queue of orders for the IO task.
+///
+/// A new order replaces the pending orders it makes obsolete, so that only the latest color or
+/// image of a key is sent to the device.
+#[derive(Clone)]
+pub(crate) struct OrderQueue {
+    pending: Arc<Mutex<PendingOrders>>,
+    wake_tx: Sender<()>,
+    wake_rx: Receiver<()>,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+struct PendingOrders {
+    orders: VecDeque<StreamDeckOrder>,
+    closed: bool,
+}
+
+impl OrderQueue {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        let (wake_tx, wake_rx) = bounded(1);
+        Self {
+            pending: Arc::new(Mutex::new(PendingOrders {
+                orders: VecDeque::new(),
+                closed: false,
+            })),
+            wake_tx,
+            wake_rx,
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    /// Queue an order, returning it if it was discarded. This never waits, so with
+    /// [`OverflowPolicy::Block`] the order is discarded instead.
+    pub(crate) fn send(&self, order: StreamDeckOrder) -> Result<(), StreamDeckOrder> {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.closed {
+            return Err(order);
+        }
+        pending.orders.retain(|queued| !order.replaces(queued));
+        // Only orders for a device count towards the capacity, so that exiting is never discarded
+        if order.device().is_some() && pending.orders.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::Block | OverflowPolicy::DropNewest => return Err(order),
+                OverflowPolicy::Grow => (),
+                OverflowPolicy::DropOldest => {
+                    while pending.orders.len() >= self.capacity
+                        && let Some(oldest) = pending
+                            .orders
+                            .iter()
+                            .position(|queued| queued.device().is_some())
+                    {
+                        pending.orders.remove(oldest);
+                    }
+                }
+            }
+        }
+        pending.orders.push_back(order);
+        let _ = self.wake_tx.try_send(());
+        Ok(())
+    }
+
+    /// Take all pending orders.
+    pub(crate) fn drain(&self) -> VecDeque<StreamDeckOrder> {
+        std::mem::take(&mut self.pending.lock().unwrap().orders)
+    }
+
+    /// Wait until an order is queued, or the timeout expires.
+    pub(crate) fn wait(&self, timeout: Duration) {
+        let _ = self.wake_rx.recv_timeout(timeout);
+    }
+
+    /// Stop accepting orders.
+    pub(crate) fn close(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.closed = true;
+        pending.orders.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::Color;
+
+    use super::*;
+    use crate::{DeviceId, StreamDeckKey};
+
+    fn color(key: u8, color: Color) -> StreamDeckOrder {
+        StreamDeckOrder::Color(StreamDeckKey::from(key), color)
+    }
+
+    fn colors(queue: &OrderQueue) -> Vec<(u8, Color)> {
+        queue
+            .drain()
+            .into_iter()
+            .filter_map(|order| match order {
+                StreamDeckOrder::Color(key, color) => Some((key.key, color)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn latest_color_of_a_key_replaces_pending_one() {
+        let queue = OrderQueue::new(10, OverflowPolicy::Block);
+        queue.send(color(0, Color::WHITE)).unwrap();
+        queue.send(color(1, Color::WHITE)).unwrap();
+        queue.send(color(0, Color::BLACK)).unwrap();
+        assert_eq!(colors(&queue), vec![(1, Color::WHITE), (0, Color::BLACK)]);
+    }
+
+    #[test]
+    fn reset_replaces_pending_orders_of_its_device_only() {
+        let queue = OrderQueue::new(10, OverflowPolicy::Block);
+        queue.send(color(0, Color::WHITE)).unwrap();
+        queue
+            .send(StreamDeckOrder::Color(
+                StreamDeckKey::new(DeviceId(1), 0),
+                Color::WHITE,
+            ))
+            .unwrap();
+        queue
+            .send(StreamDeckOrder::Brightness(DeviceId(0), 50))
+            .unwrap();
+        queue.send(StreamDeckOrder::Reset(DeviceId(0))).unwrap();
+        let orders = queue.drain();
+        assert_eq!(orders.len(), 3);
+        assert!(matches!(orders[0], StreamDeckOrder::Color(key, _) if key.device == DeviceId(1)));
+        assert!(matches!(
+            orders[1],
+            StreamDeckOrder::Brightness(DeviceId(0), 50)
+        ));
+        assert!(matches!(orders[2], StreamDeckOrder::Reset(DeviceId(0))));
+    }
+
+    #[test]
+    fn block_rejects_orders_instead_of_waiting() {
+        let queue = OrderQueue::new(2, OverflowPolicy::Block);
+        queue.send(color(0, Color::WHITE)).unwrap();
+        queue.send(color(1, Color::WHITE)).unwrap();
+        assert!(queue.send(color(2, Color::WHITE)).is_err());
+        assert_eq!(queue.drain().len(), 2);
+    }
+
+    #[test]
+    fn grow_keeps_every_order() {
+        let queue = OrderQueue::new(2, OverflowPolicy::Grow);
+        for key in 0..5 {
+            queue.send(color(key, Color::WHITE)).unwrap();
+        }
+        assert_eq!(queue.drain().len(), 5);
+    }
+
+    #[test]
+    fn drop_newest_rejects_orders_when_full() {
+        let queue = OrderQueue::new(2, OverflowPolicy::DropNewest);
+        queue.send(color(0, Color::WHITE)).unwrap();
+        queue.send(color(1, Color::WHITE)).unwrap();
+        assert!(queue.send(color(2, Color::WHITE)).is_err());
+        // Replacing a pending order makes room for it
+        queue.send(color(1, Color::BLACK)).unwrap();
+        assert_eq!(colors(&queue), vec![(0, Color::WHITE), (1, Color::BLACK)]);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_exit() {
+        let queue = OrderQueue::new(2, OverflowPolicy::DropOldest);
+        queue.send(StreamDeckOrder::Exit).unwrap();
+        for key in 0..4 {
+            queue.send(color(key, Color::WHITE)).unwrap();
+        }
+        let orders = queue.drain();
+        assert!(matches!(orders[0], StreamDeckOrder::Exit));
+        assert!(matches!(orders[1], StreamDeckOrder::Color(key, _) if key.key == 3));
+        assert_eq!(orders.len(), 2);
+    }
+
+    #[test]
+    fn closed_queue_rejects_orders() {
+        let queue = OrderQueue::new(2, OverflowPolicy::Block);
+        queue.close();
+        assert!(queue.send(color(0, Color::WHITE)).is_err());
+    }
+
+    #[test]
+    fn unbounded_messages_are_never_discarded() {
+        let (sender, receiver) = channel(2, OverflowPolicy::DropOldest);
+        sender.send_unbounded("connected").unwrap();
+        for input in ["a", "b", "c", "d"] {
+            sender.send(input).unwrap();
+        }
+        sender.send_unbounded("disconnected").unwrap();
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec!["connected", "c", "d", "disconnected"]
+        );
+    }
+
+    #[test]
+    fn drop_newest_discards_inputs_when_full() {
+        let (sender, receiver) = channel(2, OverflowPolicy::DropNewest);
+        sender.send("a").unwrap();
+        sender.send("b").unwrap();
+        assert_eq!(sender.send("c"), Err("c"));
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec!["a", "b"]);
+        sender.send("c").unwrap();
+    }
+
+    #[test]
+    fn grow_keeps_every_input() {
+        let (sender, receiver) = channel(2, OverflowPolicy::Grow);
+        for input in ["a", "b", "c"] {
+            sender.send(input).unwrap();
+        }
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn sending_fails_once_receiver_is_dropped() {
+        let (sender, receiver) = channel(2, OverflowPolicy::Block);
+        drop(receiver);
+        assert_eq!(sender.send("a"), Err("a"));
+        assert_eq!(sender.send_unbounded("b"), Err("b"));
+    }
+}
@@ -0,0 +1,380 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy_app::App;
+use bevy_ecs::{
+    message::{Message, MessageWriter},
+    resource::Resource,
+    system::{Res, ResMut, SystemParam},
+};
+use bevy_time::{Real, Time};
+
+use crate::{DeviceId, StreamDeckKey};
+
+/// Keys that must be pressed together.
+#[derive(Clone, Debug)]
+pub struct Chord {
+    pub keys: Vec<StreamDeckKey>,
+    /// Maximum delay between the first and the last key press.
+    pub window: Duration,
+    /// Hide the presses of these keys. They are delayed by up to `window`, and only sent if the
+    /// chord didn't happen.
+    pub suppress: bool,
+}
+
+impl Chord {
+    pub fn new(keys: impl IntoIterator<Item = impl Into<StreamDeckKey>>) -> Self {
+        Self {
+            keys: keys.into_iter().map(Into::into).collect(),
+            window: Duration::from_millis(200),
+            suppress: false,
+        }
+    }
+
+    pub fn with_window(mut self, window: Duration) -> Self {
+        self.window = window;
+        self
+    }
+
+    pub fn suppressing(mut self) -> Self {
+        self.suppress = true;
+        self
+    }
+}
+
+/// Identifies a registered [`Chord`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct ChordId(pub usize);
+
+/// Sent when all keys of a chord have been pressed.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StreamDeckChord(pub ChordId);
+
+/// Chords to detect.
+#[derive(Resource, Default)]
+pub struct StreamDeckChords {
+    chords: HashMap<ChordId, Chord>,
+    next_id: usize,
+    held: HashMap<StreamDeckKey, HeldKey>,
+}
+
+struct HeldKey {
+    // Real time elapsed since startup
+    pressed_at: Duration,
+    state: HeldState,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HeldState {
+    /// The press has been sent.
+    Sent,
+    /// Waiting to know if the key is part of a chord.
+    Delayed,
+    /// Part of a suppressed chord, its release is hidden too.
+    Suppressed,
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_message::<StreamDeckChord>()
+        .init_resource::<StreamDeckChords>();
+}
+
+/// What the receiver needs to detect chords in the key presses it reads.
+#[derive(SystemParam)]
+pub(crate) struct ChordDetection<'w> {
+    pub(crate) chords: ResMut<'w, StreamDeckChords>,
+    pub(crate) time: Option<Res<'w, Time<Real>>>,
+    pub(crate) triggered: MessageWriter<'w, StreamDeckChord>,
+}
+
+/// A change of key state to send, after chords have been detected.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum KeyTransition {
+    Press(StreamDeckKey),
+    Release(StreamDeckKey),
+}
+
+impl StreamDeckChords {
+    pub fn register(&mut self, chord: Chord) -> ChordId {
+        let id = ChordId(self.next_id);
+        self.next_id += 1;
+        self.chords.insert(id, chord);
+        id
+    }
+
+    pub fn unregister(&mut self, id: ChordId) -> Option<Chord> {
+        self.chords.remove(&id)
+    }
+
+    pub fn get(&self, id: ChordId) -> Option<&Chord> {
+        self.chords.get(&id)
+    }
+
+    /// Whether the key is held, but its press has not been sent.
+    pub(crate) fn is_hidden(&self, key: StreamDeckKey) -> bool {
+        self.held
+            .get(&key)
+            .is_some_and(|held| held.state != HeldState::Sent)
+    }
+
+    /// How long to delay a press of the key, if it is part of a suppressed chord.
+    fn delay(&self, key: StreamDeckKey) -> Option<Duration> {
+        self.chords
+            .values()
+            .filter(|chord| chord.suppress && chord.keys.contains(&key))
+            .map(|chord| chord.window)
+            .max()
+    }
+
+    /// Without a clock, chords can't be timed and the press is sent as is.
+    pub(crate) fn press(
+        &mut self,
+        key: StreamDeckKey,
+        now: Option<Duration>,
+        transitions: &mut Vec<KeyTransition>,
+        triggered: &mut Vec<ChordId>,
+    ) {
+        let Some(now) = now else {
+            transitions.push(KeyTransition::Press(key));
+            return;
+        };
+        let state = if self.delay(key).is_some() {
+            HeldState::Delayed
+        } else {
+            transitions.push(KeyTransition::Press(key));
+            HeldState::Sent
+        };
+        self.held.insert(
+            key,
+            HeldKey {
+                pressed_at: now,
+                state,
+            },
+        );
+
+        for (id, chord) in self
+            .chords
+            .iter()
+            .filter(|(_, chord)| chord.keys.contains(&key))
+        {
+            let complete = chord.keys.iter().all(|key| {
+                self.held.get(key).is_some_and(|held| {
+                    held.state != HeldState::Suppressed && now - held.pressed_at <= chord.window
+                })
+            });
+            if complete {
+                triggered.push(*id);
+            }
+        }
+        for id in triggered.iter() {
+            let chord = &self.chords[id];
+            if chord.suppress {
+                for key in &chord.keys {
+                    if let Some(held) = self.held.get_mut(key)
+                        && held.state == HeldState::Delayed
+                    {
+                        held.state = HeldState::Suppressed;
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn release(&mut self, key: StreamDeckKey, transitions: &mut Vec<KeyTransition>) {
+        match self.held.remove(&key).map(|held| held.state) {
+            Some(HeldState::Suppressed) => (),
+            // Too short to be part of a chord, send the press late
+            Some(HeldState::Delayed) => {
+                transitions.push(KeyTransition::Press(key));
+                transitions.push(KeyTransition::Release(key));
+            }
+            Some(HeldState::Sent) | None => transitions.push(KeyTransition::Release(key)),
+        }
+    }
+
+    /// Send the presses that waited long enough without completing a chord.
+    pub(crate) fn expire(&mut self, now: Duration, transitions: &mut Vec<KeyTransition>) {
+        let expired = self
+            .held
+            .iter()
+            .filter(|(key, held)| {
+                held.state == HeldState::Delayed
+                    && self
+                        .delay(**key)
+                        .is_none_or(|delay| now - held.pressed_at > delay)
+            })
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+        for key in expired {
+            self.held.get_mut(&key).unwrap().state = HeldState::Sent;
+            transitions.push(KeyTransition::Press(key));
+        }
+    }
+
+    /// Forget the keys of a disconnected device. Keys whose press was sent are released by the
+    /// receiver.
+    pub(crate) fn remove_device(&mut self, device: DeviceId) {
+        self.held.retain(|key, _| key.device != device);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: u8) -> StreamDeckKey {
+        StreamDeckKey::from(key)
+    }
+
+    fn press(
+        chords: &mut StreamDeckChords,
+        pressed: u8,
+        at: u64,
+    ) -> (Vec<KeyTransition>, Vec<ChordId>) {
+        let mut transitions = Vec::new();
+        let mut triggered = Vec::new();
+        chords.press(
+            key(pressed),
+            Some(Duration::from_millis(at)),
+            &mut transitions,
+            &mut triggered,
+        );
+        (transitions, triggered)
+    }
+
+    fn release(chords: &mut StreamDeckChords, released: u8) -> Vec<KeyTransition> {
+        let mut transitions = Vec::new();
+        chords.release(key(released), &mut transitions);
+        transitions
+    }
+
+    fn expire(chords: &mut StreamDeckChords, at: u64) -> Vec<KeyTransition> {
+        let mut transitions = Vec::new();
+        chords.expire(Duration::from_millis(at), &mut transitions);
+        transitions
+    }
+
+    #[test]
+    fn chord_fires_within_its_window() {
+        let mut chords = StreamDeckChords::default();
+        let chord = chords.register(Chord::new([0, 4]));
+
+        assert_eq!(
+            press(&mut chords, 0, 0),
+            (vec![KeyTransition::Press(key(0))], vec![])
+        );
+        assert_eq!(
+            press(&mut chords, 4, 150),
+            (vec![KeyTransition::Press(key(4))], vec![chord])
+        );
+        assert_eq!(
+            release(&mut chords, 0),
+            vec![KeyTransition::Release(key(0))]
+        );
+        assert_eq!(
+            release(&mut chords, 4),
+            vec![KeyTransition::Release(key(4))]
+        );
+    }
+
+    #[test]
+    fn chord_does_not_fire_after_its_window() {
+        let mut chords = StreamDeckChords::default();
+        chords.register(Chord::new([0, 4]).with_window(Duration::from_millis(100)));
+
+        press(&mut chords, 0, 0);
+        assert_eq!(
+            press(&mut chords, 4, 101),
+            (vec![KeyTransition::Press(key(4))], vec![])
+        );
+    }
+
+    #[test]
+    fn suppressed_chord_hides_its_keys() {
+        let mut chords = StreamDeckChords::default();
+        let chord = chords.register(Chord::new([0, 4]).suppressing());
+
+        assert_eq!(press(&mut chords, 0, 0), (vec![], vec![]));
+        assert!(chords.is_hidden(key(0)));
+        assert_eq!(press(&mut chords, 4, 50), (vec![], vec![chord]));
+        assert_eq!(expire(&mut chords, 1000), vec![]);
+        assert_eq!(release(&mut chords, 0), vec![]);
+        assert_eq!(release(&mut chords, 4), vec![]);
+        assert!(!chords.is_hidden(key(0)));
+    }
+
+    #[test]
+    fn suppressed_key_released_alone_is_sent_late() {
+        let mut chords = StreamDeckChords::default();
+        chords.register(Chord::new([0, 4]).suppressing());
+
+        press(&mut chords, 0, 0);
+        assert_eq!(
+            release(&mut chords, 0),
+            vec![KeyTransition::Press(key(0)), KeyTransition::Release(key(0))]
+        );
+    }
+
+    #[test]
+    fn suppressed_key_held_past_the_window_is_sent() {
+        let mut chords = StreamDeckChords::default();
+        chords.register(Chord::new([0, 4]).suppressing());
+
+        press(&mut chords, 0, 0);
+        assert_eq!(expire(&mut chords, 200), vec![]);
+        assert_eq!(expire(&mut chords, 201), vec![KeyTransition::Press(key(0))]);
+        assert!(!chords.is_hidden(key(0)));
+        // Too late for the chord
+        assert_eq!(press(&mut chords, 4, 250), (vec![], vec![]));
+        assert_eq!(
+            release(&mut chords, 0),
+            vec![KeyTransition::Release(key(0))]
+        );
+    }
+
+    #[test]
+    fn other_keys_are_not_delayed() {
+        let mut chords = StreamDeckChords::default();
+        chords.register(Chord::new([0, 4]).suppressing());
+
+        assert_eq!(
+            press(&mut chords, 1, 0),
+            (vec![KeyTransition::Press(key(1))], vec![])
+        );
+    }
+
+    #[test]
+    fn presses_are_sent_as_is_without_a_clock() {
+        let mut chords = StreamDeckChords::default();
+        chords.register(Chord::new([0, 4]).suppressing());
+
+        let mut transitions = Vec::new();
+        let mut triggered = Vec::new();
+        chords.press(key(0), None, &mut transitions, &mut triggered);
+        assert_eq!(transitions, vec![KeyTransition::Press(key(0))]);
+        assert!(!chords.is_hidden(key(0)));
+    }
+
+    #[test]
+    fn unregistered_chord_does_not_fire() {
+        let mut chords = StreamDeckChords::default();
+        let chord = chords.register(Chord::new([0, 4]));
+        assert!(chords.unregister(chord).is_some());
+
+        press(&mut chords, 0, 0);
+        assert_eq!(
+            press(&mut chords, 4, 10),
+            (vec![KeyTransition::Press(key(4))], vec![])
+        );
+    }
+
+    #[test]
+    fn disconnected_device_forgets_hidden_keys() {
+        let mut chords = StreamDeckChords::default();
+        chords.register(Chord::new([0, 4]).suppressing());
+
+        press(&mut chords, 0, 0);
+        chords.remove_device(DeviceId::default());
+        assert!(!chords.is_hidden(key(0)));
+        assert_eq!(expire(&mut chords, 1000), vec![]);
+    }
+}
@@ -0,0 +1,199 @@
+use std::fmt;
+
+use bevy_color::{ColorToPacked, LinearRgba, Srgba};
+use bevy_image::Image;
+use bevy_render::render_resource::TextureFormat;
+use image::{DynamicImage, ImageBuffer};
+
+/// Why an [`Image`] could not be displayed on a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageConversionError {
+    /// The texture format is not supported.
+    UnsupportedFormat(TextureFormat),
+    /// The image only exists on the GPU.
+    MissingData,
+    /// The data does not match the size of the image.
+    InvalidSize,
+}
+
+impl fmt::Display for ImageConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageConversionError::UnsupportedFormat(format) => {
+                write!(f, "unsupported texture format {format:?}")
+            }
+            ImageConversionError::MissingData => write!(f, "image has no data on the CPU"),
+            ImageConversionError::InvalidSize => write!(f, "image data does not match its size"),
+        }
+    }
+}
+
+impl std::error::Error for ImageConversionError {}
+
+/// Convert a Bevy [`Image`] to an RGBA image.
+///
+/// 8 and 16 bits formats are kept as is, float formats are considered linear and converted to sRGB.
+pub fn image_to_dynamic(image: &Image) -> Result<DynamicImage, ImageConversionError> {
+    let data = image
+        .data
+        .as_ref()
+        .ok_or(ImageConversionError::MissingData)?;
+
+    let rgba = match image.texture_descriptor.format {
+        TextureFormat::Rgba8UnormSrgb | TextureFormat::Rgba8Unorm => data.clone(),
+        TextureFormat::Bgra8UnormSrgb | TextureFormat::Bgra8Unorm => data
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect(),
+        TextureFormat::R8Unorm => data
+            .iter()
+            .flat_map(|value| [*value, *value, *value, 255])
+            .collect(),
+        TextureFormat::Rg8Unorm => data
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[1], 0, 255])
+            .collect(),
+        TextureFormat::R16Unorm => data
+            .chunks_exact(2)
+            .map(|value| (u16::from_le_bytes([value[0], value[1]]) >> 8) as u8)
+            .flat_map(|value| [value, value, value, 255])
+            .collect(),
+        TextureFormat::Rgba16Unorm => data
+            .chunks_exact(2)
+            .map(|value| (u16::from_le_bytes([value[0], value[1]]) >> 8) as u8)
+            .collect(),
+        TextureFormat::R16Float => data
+            .chunks_exact(2)
+            .map(|value| f16_to_f32(u16::from_le_bytes([value[0], value[1]])))
+            .flat_map(|value| linear_to_srgb([value, value, value, 1.0]))
+            .collect(),
+        TextureFormat::Rgba16Float => data
+            .chunks_exact(8)
+            .flat_map(|pixel| {
+                let channel = |i: usize| f16_to_f32(u16::from_le_bytes([pixel[i], pixel[i + 1]]));
+                linear_to_srgb([channel(0), channel(2), channel(4), channel(6)])
+            })
+            .collect(),
+        TextureFormat::R32Float => data
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+            .flat_map(|value| linear_to_srgb([value, value, value, 1.0]))
+            .collect(),
+        TextureFormat::Rgba32Float => data
+            .chunks_exact(16)
+            .flat_map(|pixel| {
+                let channel = |i: usize| {
+                    f32::from_le_bytes([pixel[i], pixel[i + 1], pixel[i + 2], pixel[i + 3]])
+                };
+                linear_to_srgb([channel(0), channel(4), channel(8), channel(12)])
+            })
+            .collect(),
+        format => return Err(ImageConversionError::UnsupportedFormat(format)),
+    };
+
+    ImageBuffer::from_raw(
+        image.texture_descriptor.size.width,
+        image.texture_descriptor.size.height,
+        rgba,
+    )
+    .map(DynamicImage::ImageRgba8)
+    .ok_or(ImageConversionError::InvalidSize)
+}
+
+fn linear_to_srgb([red, green, blue, alpha]: [f32; 4]) -> [u8; 4] {
+    Srgba::from(LinearRgba::new(red, green, blue, alpha)).to_u8_array()
+}
+
+/// Convert a half precision float, as stored in 16 bits float textures.
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((half >> 10) & 0x1f) as i32;
+    let mantissa = (half & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(format: TextureFormat, data: Vec<u8>) -> [u8; 4] {
+        let mut image = Image {
+            data: Some(data),
+            ..Image::default()
+        };
+        image.texture_descriptor.format = format;
+        image_to_dynamic(&image)
+            .unwrap()
+            .to_rgba8()
+            .get_pixel(0, 0)
+            .0
+    }
+
+    #[test]
+    fn decodes_half_floats() {
+        assert_eq!(f16_to_f32(0x0000), 0.0);
+        assert_eq!(f16_to_f32(0x3c00), 1.0);
+        assert_eq!(f16_to_f32(0x3800), 0.5);
+        assert_eq!(f16_to_f32(0xc000), -2.0);
+        assert_eq!(f16_to_f32(0x7bff), 65504.0);
+        // 1365 / 4096, the closest half float to a third
+        assert_eq!(f16_to_f32(0x3555), f32::from_bits(0x3eaa_a000));
+        // Smallest normal and subnormal numbers
+        assert_eq!(f16_to_f32(0x0400), 2f32.powi(-14));
+        assert_eq!(f16_to_f32(0x0001), 2f32.powi(-24));
+        assert_eq!(f16_to_f32(0x8001), -(2f32.powi(-24)));
+        assert_eq!(f16_to_f32(0x7c00), f32::INFINITY);
+        assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+        assert!(f16_to_f32(0x7e00).is_nan());
+    }
+
+    #[test]
+    fn swaps_bgra_channels() {
+        assert_eq!(
+            pixel(TextureFormat::Bgra8UnormSrgb, vec![1, 2, 3, 4]),
+            [3, 2, 1, 4]
+        );
+    }
+
+    #[test]
+    fn converts_linear_floats_to_srgb() {
+        let half = |value: u16| value.to_le_bytes();
+        let data = [half(0x3c00), half(0x0000), half(0x3800), half(0x3c00)].concat();
+        assert_eq!(
+            pixel(TextureFormat::Rgba16Float, data),
+            linear_to_srgb([1.0, 0.0, 0.5, 1.0])
+        );
+        assert_eq!(
+            pixel(TextureFormat::R32Float, 1.0f32.to_le_bytes().to_vec()),
+            [255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_data() {
+        let mut image = Image {
+            data: Some(vec![0; 3]),
+            ..Image::default()
+        };
+        assert_eq!(
+            image_to_dynamic(&image).unwrap_err(),
+            ImageConversionError::InvalidSize
+        );
+        image.data = None;
+        assert_eq!(
+            image_to_dynamic(&image).unwrap_err(),
+            ImageConversionError::MissingData
+        );
+        image.texture_descriptor.format = TextureFormat::Depth32Float;
+        image.data = Some(vec![0; 4]);
+        assert_eq!(
+            image_to_dynamic(&image).unwrap_err(),
+            ImageConversionError::UnsupportedFormat(TextureFormat::Depth32Float)
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use bevy_ecs::{
+    resource::Resource,
+    system::{ResMut, SystemParam},
+};
+use bevy_input::ButtonInput;
+
+use crate::DeviceId;
+
+/// An encoder (dial) of a device, starting at 0 on the left.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct StreamDeckEncoder {
+    pub device: DeviceId,
+    pub encoder: u8,
+}
+
+impl StreamDeckEncoder {
+    pub fn new(device: DeviceId, encoder: u8) -> Self {
+        Self { device, encoder }
+    }
+}
+
+impl From<u8> for StreamDeckEncoder {
+    fn from(encoder: u8) -> Self {
+        Self::new(DeviceId::default(), encoder)
+    }
+}
+
+/// Rotation of the encoders, in ticks, positive clockwise.
+#[derive(Resource, Debug, Default)]
+pub struct EncoderRotation {
+    total: HashMap<StreamDeckEncoder, i32>,
+    delta: HashMap<StreamDeckEncoder, i32>,
+}
+
+impl EncoderRotation {
+    /// Ticks since the encoder was connected or reset.
+    pub fn total(&self, encoder: impl Into<StreamDeckEncoder>) -> i32 {
+        self.total.get(&encoder.into()).copied().unwrap_or_default()
+    }
+
+    /// Ticks during the last update.
+    pub fn delta(&self, encoder: impl Into<StreamDeckEncoder>) -> i32 {
+        self.delta.get(&encoder.into()).copied().unwrap_or_default()
+    }
+
+    pub fn reset(&mut self, encoder: impl Into<StreamDeckEncoder>) {
+        self.total.remove(&encoder.into());
+    }
+
+    pub(crate) fn rotate(&mut self, encoder: StreamDeckEncoder, ticks: i8) {
+        *self.total.entry(encoder).or_default() += ticks as i32;
+        *self.delta.entry(encoder).or_default() += ticks as i32;
+    }
+
+    pub(crate) fn clear_delta(&mut self) {
+        self.delta.clear();
+    }
+
+    pub(crate) fn remove_device(&mut self, device: DeviceId) {
+        self.total.retain(|encoder, _| encoder.device != device);
+        self.delta.retain(|encoder, _| encoder.device != device);
+    }
+}
+
+/// Encoder state updated by the receiver.
+#[derive(SystemParam)]
+pub(crate) struct EncoderInputs<'w> {
+    pub(crate) buttons: ResMut<'w, ButtonInput<StreamDeckEncoder>>,
+    pub(crate) rotation: ResMut<'w, EncoderRotation>,
+}
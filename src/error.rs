@@ -0,0 +1,91 @@
+use std::fmt;
+
+use bevy_ecs::message::Message;
+use bevy_math::{URect, UVec2};
+use streamdeck::Error;
+
+#[cfg(feature = "image_compatibility")]
+use crate::ImageConversionError;
+use crate::{DeviceId, StreamDeckKey};
+
+/// What the plugin was doing when a [`StreamDeckError`] happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderKind {
+    Connect,
+    ReadButtons,
+    Reset,
+    Brightness,
+    Color,
+    Image,
+    Touchstrip,
+}
+
+/// An error communicating with a device.
+#[derive(Message, Debug)]
+pub struct StreamDeckError {
+    /// The device, if the error happened while connected.
+    pub device: Option<DeviceId>,
+    /// The key, if the error happened while updating a key.
+    pub key: Option<u8>,
+    pub order: OrderKind,
+    pub error: Error,
+}
+
+/// Why an order could not be sent to a device.
+#[derive(Debug)]
+pub enum OrderError {
+    /// The device is not connected.
+    NotConnected(DeviceId),
+    /// The device doesn't have this key.
+    KeyOutOfRange { key: StreamDeckKey, keys: u8 },
+    /// The device doesn't support this order, for example a device without a display.
+    Unsupported(DeviceId),
+    /// The region is outside of the touch strip.
+    RegionOutOfRange { region: URect, size: UVec2 },
+    /// The image can't be displayed.
+    #[cfg(feature = "image_compatibility")]
+    UnsupportedImage(ImageConversionError),
+    /// The order queue is full, or the IO task has stopped.
+    QueueFull,
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderError::NotConnected(device) => write!(f, "device {} is not connected", device.0),
+            OrderError::KeyOutOfRange { key, keys } => write!(
+                f,
+                "key {} is out of range, device {} has {} keys",
+                key.key, key.device.0, keys
+            ),
+            OrderError::Unsupported(device) => {
+                write!(f, "device {} doesn't support this order", device.0)
+            }
+            OrderError::RegionOutOfRange { region, size } => write!(
+                f,
+                "region {:?} is out of the touch strip of size {}",
+                region, size
+            ),
+            #[cfg(feature = "image_compatibility")]
+            OrderError::UnsupportedImage(error) => write!(f, "{error}"),
+            OrderError::QueueFull => write!(f, "order queue is full"),
+        }
+    }
+}
+
+impl std::error::Error for OrderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "image_compatibility")]
+            OrderError::UnsupportedImage(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "image_compatibility")]
+impl From<ImageConversionError> for OrderError {
+    fn from(error: ImageConversionError) -> Self {
+        OrderError::UnsupportedImage(error)
+    }
+}
@@ -0,0 +1,275 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy_app::{App, PreUpdate};
+use bevy_ecs::{
+    message::{Message, MessageReader, MessageWriter},
+    resource::Resource,
+    schedule::{IntoScheduleConfigs, common_conditions::resource_exists},
+    system::{Local, Res},
+};
+use bevy_time::{Real, Time};
+
+use crate::{StreamDeckInput, StreamDeckKey, receiver};
+
+/// Gestures recognised from key presses, see [`GestureSettings`] for their timing.
+#[derive(Message, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StreamDeckGesture {
+    /// A short press. When double taps are detected, it is sent once the delay for a second tap
+    /// has passed.
+    Tap(StreamDeckKey),
+    DoubleTap(StreamDeckKey),
+    /// The key has been held long enough. It is sent while the key is still pressed.
+    LongPress(StreamDeckKey),
+    /// Sent at regular intervals while the key is held after a long press, counting from 1.
+    Repeat(StreamDeckKey, u32),
+}
+
+/// Timing of the [`StreamDeckGesture`]s, in real time so that pausing the game doesn't affect
+/// them. Can be changed at any time.
+#[derive(Resource, Clone, Debug)]
+pub struct GestureSettings {
+    /// How long a key must be held for a long press.
+    pub long_press: Duration,
+    /// Maximum delay between two taps for a double tap. `None` disables double taps, so that taps
+    /// are sent on release.
+    pub double_tap: Option<Duration>,
+    /// Interval between repeats while a key is held after a long press. `None` disables repeats.
+    pub repeat: Option<Duration>,
+}
+
+impl Default for GestureSettings {
+    fn default() -> Self {
+        Self {
+            long_press: Duration::from_millis(500),
+            double_tap: Some(Duration::from_millis(300)),
+            repeat: Some(Duration::from_millis(100)),
+        }
+    }
+}
+
+#[derive(Default)]
+struct KeyGesture {
+    pressed_at: Option<Duration>,
+    long_pressed: bool,
+    repeats: u32,
+    // Release of a tap that may become a double tap
+    pending_tap: Option<Duration>,
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_message::<StreamDeckGesture>()
+        .init_resource::<GestureSettings>()
+        .add_systems(
+            PreUpdate,
+            detect_gestures
+                .after(receiver)
+                .run_if(resource_exists::<Time<Real>>),
+        );
+}
+
+fn detect_gestures(
+    time: Res<Time<Real>>,
+    settings: Res<GestureSettings>,
+    mut inputs: MessageReader<StreamDeckInput>,
+    mut gestures: MessageWriter<StreamDeckGesture>,
+    mut keys: Local<HashMap<StreamDeckKey, KeyGesture>>,
+) {
+    let now = time.elapsed();
+    for input in inputs.read() {
+        match input {
+            StreamDeckInput::Press(key) => {
+                let state = keys.entry(*key).or_default();
+                state.pressed_at = Some(now);
+                state.long_pressed = false;
+                state.repeats = 0;
+            }
+            StreamDeckInput::Release(key) => {
+                let state = keys.entry(*key).or_default();
+                // Releasing a long press is not a tap
+                if state.pressed_at.take().is_none() || state.long_pressed {
+                    continue;
+                }
+                let Some(double_tap) = settings.double_tap else {
+                    gestures.write(StreamDeckGesture::Tap(*key));
+                    continue;
+                };
+                match state.pending_tap.take() {
+                    Some(first) if now - first <= double_tap => {
+                        gestures.write(StreamDeckGesture::DoubleTap(*key));
+                    }
+                    Some(_) => {
+                        gestures.write(StreamDeckGesture::Tap(*key));
+                        state.pending_tap = Some(now);
+                    }
+                    None => state.pending_tap = Some(now),
+                }
+            }
+            StreamDeckInput::Disconnected(device) => {
+                keys.retain(|key, _| key.device != *device);
+            }
+            _ => (),
+        }
+    }
+
+    for (key, state) in keys.iter_mut() {
+        if let Some(pressed_at) = state.pressed_at {
+            let held = now - pressed_at;
+            if !state.long_pressed && held >= settings.long_press {
+                // The previous tap can't be followed by a second one anymore
+                if state.pending_tap.take().is_some() {
+                    gestures.write(StreamDeckGesture::Tap(*key));
+                }
+                state.long_pressed = true;
+                gestures.write(StreamDeckGesture::LongPress(*key));
+            }
+            if let Some(repeat) = settings.repeat.filter(|repeat| !repeat.is_zero()) {
+                while state.long_pressed
+                    && held >= settings.long_press + repeat * (state.repeats + 1)
+                {
+                    state.repeats += 1;
+                    gestures.write(StreamDeckGesture::Repeat(*key, state.repeats));
+                }
+            }
+        } else if let Some(released_at) = state.pending_tap
+            && settings
+                .double_tap
+                .is_none_or(|double_tap| now - released_at > double_tap)
+        {
+            state.pending_tap = None;
+            gestures.write(StreamDeckGesture::Tap(*key));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy_app::Update;
+    use bevy_ecs::message::Messages;
+
+    use super::*;
+
+    const KEY: StreamDeckKey = StreamDeckKey {
+        device: crate::DeviceId(0),
+        key: 0,
+    };
+
+    struct Harness {
+        app: App,
+        start: Instant,
+    }
+
+    impl Harness {
+        fn new(settings: GestureSettings) -> Self {
+            let start = Instant::now();
+            let mut time = Time::<Real>::new(start);
+            time.update_with_instant(start);
+            let mut app = App::new();
+            app.add_message::<StreamDeckInput>()
+                .add_message::<StreamDeckGesture>()
+                .insert_resource(settings)
+                .insert_resource(time)
+                .add_systems(Update, detect_gestures);
+            Self { app, start }
+        }
+
+        /// Update at a time in milliseconds, with an optional input, returning the gestures sent.
+        fn update(&mut self, at: u64, input: Option<StreamDeckInput>) -> Vec<StreamDeckGesture> {
+            if let Some(input) = input {
+                self.app.world_mut().write_message(input);
+            }
+            self.app
+                .world_mut()
+                .resource_mut::<Time<Real>>()
+                .update_with_instant(self.start + Duration::from_millis(at));
+            self.app.update();
+            self.app
+                .world_mut()
+                .resource_mut::<Messages<StreamDeckGesture>>()
+                .drain()
+                .collect()
+        }
+
+        fn press(&mut self, at: u64) -> Vec<StreamDeckGesture> {
+            self.update(at, Some(StreamDeckInput::Press(KEY)))
+        }
+
+        fn release(&mut self, at: u64) -> Vec<StreamDeckGesture> {
+            self.update(at, Some(StreamDeckInput::Release(KEY)))
+        }
+    }
+
+    #[test]
+    fn tap_is_sent_on_release_without_double_taps() {
+        let mut harness = Harness::new(GestureSettings {
+            double_tap: None,
+            ..GestureSettings::default()
+        });
+        assert_eq!(harness.press(0), vec![]);
+        assert_eq!(harness.release(50), vec![StreamDeckGesture::Tap(KEY)]);
+    }
+
+    #[test]
+    fn tap_waits_for_a_second_tap() {
+        let mut harness = Harness::new(GestureSettings::default());
+        harness.press(0);
+        assert_eq!(harness.release(50), vec![]);
+        assert_eq!(harness.update(300, None), vec![]);
+        assert_eq!(harness.update(400, None), vec![StreamDeckGesture::Tap(KEY)]);
+        assert_eq!(harness.update(1000, None), vec![]);
+    }
+
+    #[test]
+    fn double_tap_replaces_taps() {
+        let mut harness = Harness::new(GestureSettings::default());
+        harness.press(0);
+        harness.release(50);
+        harness.press(100);
+        assert_eq!(
+            harness.release(150),
+            vec![StreamDeckGesture::DoubleTap(KEY)]
+        );
+        assert_eq!(harness.update(1000, None), vec![]);
+    }
+
+    #[test]
+    fn long_press_then_repeats() {
+        let mut harness = Harness::new(GestureSettings::default());
+        harness.press(0);
+        assert_eq!(harness.update(499, None), vec![]);
+        assert_eq!(
+            harness.update(500, None),
+            vec![StreamDeckGesture::LongPress(KEY)]
+        );
+        assert_eq!(
+            harness.update(720, None),
+            vec![
+                StreamDeckGesture::Repeat(KEY, 1),
+                StreamDeckGesture::Repeat(KEY, 2)
+            ]
+        );
+        // Releasing a long press is not a tap
+        assert_eq!(harness.release(750), vec![]);
+        assert_eq!(harness.update(2000, None), vec![]);
+    }
+
+    #[test]
+    fn pending_tap_is_sent_before_a_long_press() {
+        let mut harness = Harness::new(GestureSettings {
+            repeat: None,
+            ..GestureSettings::default()
+        });
+        harness.press(0);
+        harness.release(50);
+        harness.press(100);
+        assert_eq!(
+            harness.update(600, None),
+            vec![
+                StreamDeckGesture::Tap(KEY),
+                StreamDeckGesture::LongPress(KEY)
+            ]
+        );
+        assert_eq!(harness.update(2000, None), vec![]);
+    }
+}
@@ -0,0 +1,125 @@
+use image::{DynamicImage, Rgba, RgbaImage, imageops};
+
+use crate::{ImageMode, Kind, device_rgb, process_image};
+
+/// How an image is displayed across all keys of a device.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GridMode {
+    pub image_mode: ImageMode,
+    /// Pixels hidden between two keys, so that the image lines up with the physical gaps.
+    pub gap: u32,
+}
+
+/// Number of columns and rows of keys on this kind of device.
+pub fn key_layout(kind: Kind) -> (u8, u8) {
+    match kind.keys() {
+        6 => (3, 2),
+        8 => (4, 2),
+        15 => (5, 3),
+        32 => (8, 4),
+        keys => (keys, 1),
+    }
+}
+
+/// Resize an image to cover all keys, and slice it into one image per key, in key order.
+pub(crate) fn slice_image(
+    kind: Kind,
+    dynamic_image: DynamicImage,
+    grid_mode: &GridMode,
+) -> Vec<DynamicImage> {
+    let (columns, rows) = key_layout(kind);
+    let (columns, rows) = (columns as u32, rows as u32);
+    if columns == 0 {
+        return Vec::new();
+    }
+    let (width, height) = kind.image_size();
+    let (width, height) = (width as u32, height as u32);
+
+    let (grid_width, grid_height) = (
+        columns * width + (columns - 1) * grid_mode.gap,
+        rows * height + (rows - 1) * grid_mode.gap,
+    );
+    let processed = process_image(
+        (grid_width, grid_height),
+        dynamic_image,
+        &grid_mode.image_mode,
+    );
+    // The image may be smaller than the grid when its aspect ratio is kept, center it so that all
+    // keys get a full image
+    let background = grid_mode
+        .image_mode
+        .background
+        .map(|color| {
+            let [red, green, blue] = device_rgb(color);
+            [red, green, blue, 255]
+        })
+        .unwrap_or_default();
+    let mut dynamic_image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+        grid_width,
+        grid_height,
+        Rgba(background),
+    ));
+    imageops::overlay(
+        &mut dynamic_image,
+        &processed,
+        ((grid_width - processed.width()) / 2) as i64,
+        ((grid_height - processed.height()) / 2) as i64,
+    );
+
+    (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            dynamic_image.crop_imm(
+                column * (width + grid_mode.gap),
+                row * (height + grid_mode.gap),
+                width,
+                height,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::Color;
+
+    use super::*;
+    use crate::{ImageResize, device_rgb};
+
+    #[test]
+    fn slices_one_key_sized_tile_per_key_with_gaps() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(100, 50));
+        let grid_mode = GridMode {
+            gap: 10,
+            ..Default::default()
+        };
+        let tiles = slice_image(Kind::Mini, image, &grid_mode);
+        assert_eq!(tiles.len(), 6);
+        assert!(
+            tiles
+                .iter()
+                .all(|tile| (tile.width(), tile.height()) == (80, 80))
+        );
+    }
+
+    #[test]
+    fn fills_around_the_image_like_its_background() {
+        let color = Color::srgb(0.5, 0.25, 0.75);
+        // A transparent wide image, kept to its aspect ratio, covers the grid from y = 72 to 98
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(1000, 100));
+        let grid_mode = GridMode {
+            image_mode: ImageMode {
+                resize: ImageResize::Aspect,
+                background: Some(color),
+                ..Default::default()
+            },
+            gap: 10,
+        };
+        let tiles = slice_image(Kind::Mini, image, &grid_mode);
+        let [red, green, blue] = device_rgb(color);
+        let outside = tiles[0].to_rgba8().get_pixel(0, 0).0;
+        let inside = tiles[1].to_rgba8().get_pixel(40, 75).0;
+        assert_eq!(outside, [red, green, blue, 255]);
+        assert_eq!(inside, outside);
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use bevy_app::{App, PreUpdate};
+use bevy_ecs::{
+    message::MessageReader,
+    resource::Resource,
+    schedule::{IntoScheduleConfigs, common_conditions::resource_exists},
+    system::{Res, ResMut},
+};
+use bevy_time::{Real, Time};
+
+use crate::{DeviceId, StreamDeck, StreamDeckInput, receiver};
+
+/// Dim devices when no key was pressed for a while. The next key press restores their brightness.
+#[derive(Clone, Copy, Debug)]
+pub struct IdlePolicy {
+    pub after: Duration,
+    /// Brightness while idle, from 0 to 100. 0 turns the screen off.
+    pub brightness: u8,
+}
+
+#[derive(Resource)]
+pub(crate) struct IdleState {
+    policy: IdlePolicy,
+    last_activity: HashMap<DeviceId, Duration>,
+    dimmed: HashSet<DeviceId>,
+}
+
+impl IdleState {
+    pub(crate) fn new(policy: IdlePolicy) -> Self {
+        Self {
+            policy,
+            last_activity: HashMap::new(),
+            dimmed: HashSet::new(),
+        }
+    }
+}
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(
+        PreUpdate,
+        dim_idle_devices
+            .after(receiver)
+            .run_if(resource_exists::<IdleState>)
+            .run_if(resource_exists::<Time<Real>>),
+    );
+}
+
+fn dim_idle_devices(
+    time: Res<Time<Real>>,
+    streamdeck: Res<StreamDeck>,
+    mut inputs: MessageReader<StreamDeckInput>,
+    mut idle: ResMut<IdleState>,
+) {
+    let now = time.elapsed();
+    for input in inputs.read() {
+        let device = match input {
+            StreamDeckInput::Press(key) => key.device,
+            StreamDeckInput::EncoderPress(encoder) | StreamDeckInput::EncoderTwist(encoder, _) => {
+                encoder.device
+            }
+            StreamDeckInput::Touch(device, _) => *device,
+            // A reconnected device is back to its last brightness
+            StreamDeckInput::Connected(device, _) => {
+                idle.dimmed.remove(device);
+                *device
+            }
+            StreamDeckInput::Disconnected(device) => {
+                idle.last_activity.remove(device);
+                idle.dimmed.remove(device);
+                continue;
+            }
+            StreamDeckInput::Release(_) | StreamDeckInput::EncoderRelease(_) => continue,
+        };
+        idle.last_activity.insert(device, now);
+        if idle.dimmed.remove(&device) {
+            streamdeck.set_device_brightness(device, streamdeck.brightness(device).unwrap_or(100));
+        }
+    }
+
+    let IdleState {
+        policy,
+        last_activity,
+        dimmed,
+    } = &mut *idle;
+    for (device, last) in last_activity.iter() {
+        if now - *last >= policy.after && !dimmed.contains(device) {
+            // Not remembered as the device brightness, so that it can be restored
+            let _ = streamdeck.dim_device(*device, policy.brightness);
+            dimmed.insert(*device);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::HashSet;
+
+use bevy_app::{App, PostUpdate, PreUpdate};
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+use bevy_asset::{AssetEvent, Assets, Handle};
+use bevy_color::Color;
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+use bevy_ecs::schedule::common_conditions::resource_exists;
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    lifecycle::RemovedComponents,
+    message::MessageReader,
+    query::Changed,
+    schedule::IntoScheduleConfigs,
+    system::{Commands, Local, Query, Res},
+};
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+use bevy_image::Image;
+
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+use crate::bindings::image_changed;
+use crate::{OrderError, StreamDeck, StreamDeckInput, StreamDeckKey, receiver};
+
+/// The key an entity represents. An entity is spawned for each key when a device is connected,
+/// unless one already exists for that key.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StreamDeckKeyIndex(pub StreamDeckKey);
+
+/// Color displayed on the key.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct KeyColor(pub Color);
+
+/// Image displayed on the key, sent once loaded and again when modified.
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+#[derive(Component, Clone, Debug)]
+pub struct KeyImage(pub Handle<Image>);
+
+/// Marks a key that is currently pressed.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct KeyPressed;
+
+pub(crate) fn plugin(app: &mut App) {
+    app.add_systems(
+        PreUpdate,
+        (spawn_key_entities, update_key_pressed).after(receiver),
+    )
+    .add_systems(PostUpdate, (push_key_colors, reset_removed_colors));
+    #[cfg(all(feature = "assets", feature = "image_compatibility"))]
+    app.add_systems(
+        PostUpdate,
+        (
+            push_key_images.run_if(resource_exists::<Assets<Image>>),
+            reset_removed_images,
+        ),
+    );
+}
+
+fn spawn_key_entities(
+    mut commands: Commands,
+    mut inputs: MessageReader<StreamDeckInput>,
+    keys: Query<&StreamDeckKeyIndex>,
+) {
+    for input in inputs.read() {
+        if let StreamDeckInput::Connected(device, kind) = input {
+            for key in 0..kind.keys() {
+                let key = StreamDeckKey::new(*device, key);
+                if !keys.iter().any(|index| index.0 == key) {
+                    commands.spawn(StreamDeckKeyIndex(key));
+                }
+            }
+        }
+    }
+}
+
+fn update_key_pressed(
+    mut commands: Commands,
+    mut inputs: MessageReader<StreamDeckInput>,
+    keys: Query<(Entity, &StreamDeckKeyIndex)>,
+) {
+    for input in inputs.read() {
+        let (key, pressed) = match input {
+            StreamDeckInput::Press(key) => (key, true),
+            StreamDeckInput::Release(key) => (key, false),
+            _ => continue,
+        };
+        for (entity, _) in keys.iter().filter(|(_, index)| index.0 == *key) {
+            if pressed {
+                commands.entity(entity).insert(KeyPressed);
+            } else {
+                commands.entity(entity).remove::<KeyPressed>();
+            }
+        }
+    }
+}
+
+/// Send changed colors. Keys whose device is not connected yet are retried each frame.
+fn push_key_colors(
+    streamdeck: Res<StreamDeck>,
+    changed: Query<Entity, Changed<KeyColor>>,
+    keys: Query<(&StreamDeckKeyIndex, &KeyColor)>,
+    mut pending: Local<HashSet<Entity>>,
+) {
+    pending.extend(changed.iter());
+    pending.retain(|entity| {
+        let Ok((index, color)) = keys.get(*entity) else {
+            return false;
+        };
+        matches!(
+            streamdeck.try_set_key_color(index.0, color.0),
+            Err(OrderError::NotConnected(_))
+        )
+    });
+}
+
+/// Send changed or modified images. Keys whose image is not loaded or whose device is not
+/// connected yet are retried each frame.
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+fn push_key_images(
+    streamdeck: Res<StreamDeck>,
+    images: Res<Assets<Image>>,
+    mut image_events: MessageReader<AssetEvent<Image>>,
+    changed: Query<Entity, Changed<KeyImage>>,
+    keys: Query<(Entity, &StreamDeckKeyIndex, &KeyImage)>,
+    mut pending: Local<HashSet<Entity>>,
+) {
+    pending.extend(changed.iter());
+    for event in image_events.read() {
+        pending.extend(
+            keys.iter()
+                .filter(|(_, _, image)| image_changed(event, image.0.id()))
+                .map(|(entity, _, _)| entity),
+        );
+    }
+    pending.retain(|entity| {
+        let Ok((_, index, image)) = keys.get(*entity) else {
+            return false;
+        };
+        let Some(image) = images.get(&image.0) else {
+            return true;
+        };
+        matches!(
+            streamdeck.try_set_key_image(index.0, image),
+            Err(OrderError::NotConnected(_))
+        )
+    });
+}
+
+/// Clear keys that no longer have a color.
+fn reset_removed_colors(
+    streamdeck: Res<StreamDeck>,
+    mut removed: RemovedComponents<KeyColor>,
+    keys: Query<&StreamDeckKeyIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(index) = keys.get(entity) {
+            streamdeck.reset_key(index.0);
+        }
+    }
+}
+
+/// Clear keys that no longer have an image.
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+fn reset_removed_images(
+    streamdeck: Res<StreamDeck>,
+    mut removed: RemovedComponents<KeyImage>,
+    keys: Query<&StreamDeckKeyIndex>,
+) {
+    for entity in removed.read() {
+        if let Ok(index) = keys.get(entity) {
+            streamdeck.reset_key(index.0);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use streamdeck::pids;
+
+/// Product id of the Stream Deck Pedal, not supported by `streamdeck`.
+const PEDAL_PRODUCT_ID: u16 = 0x0086;
+
+/// A model of Stream Deck.
+///
+/// The models of [`streamdeck::Kind`], and the ones it doesn't support like the Pedal, which are
+/// only read from their raw input reports.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Kind {
+    Original,
+    OriginalV2,
+    Mini,
+    RevisedMini,
+    Xl,
+    Mk2,
+    Plus,
+    Module6Keys,
+    Module15Keys,
+    Module32Keys,
+    /// Three keys, without a display.
+    Pedal,
+}
+
+impl Kind {
+    /// Recognise a device from its product id.
+    pub fn from_product_id(product_id: u16) -> Option<Self> {
+        Some(match product_id {
+            pids::ORIGINAL => Kind::Original,
+            pids::ORIGINAL_V2 => Kind::OriginalV2,
+            pids::MINI => Kind::Mini,
+            pids::REVISED_MINI => Kind::RevisedMini,
+            pids::XL => Kind::Xl,
+            pids::MK2 => Kind::Mk2,
+            pids::PLUS => Kind::Plus,
+            pids::MODULE_6_KEYS => Kind::Module6Keys,
+            pids::MODULE_15_KEYS => Kind::Module15Keys,
+            pids::MODULE_32_KEYS => Kind::Module32Keys,
+            PEDAL_PRODUCT_ID => Kind::Pedal,
+            _ => return None,
+        })
+    }
+
+    /// The same model in `streamdeck`, if it supports it.
+    pub fn streamdeck_kind(self) -> Option<streamdeck::Kind> {
+        Some(match self {
+            Kind::Original => streamdeck::Kind::Original,
+            Kind::OriginalV2 => streamdeck::Kind::OriginalV2,
+            Kind::Mini => streamdeck::Kind::Mini,
+            Kind::RevisedMini => streamdeck::Kind::RevisedMini,
+            Kind::Xl => streamdeck::Kind::Xl,
+            Kind::Mk2 => streamdeck::Kind::Mk2,
+            Kind::Plus => streamdeck::Kind::Plus,
+            Kind::Module6Keys => streamdeck::Kind::Module6Keys,
+            Kind::Module15Keys => streamdeck::Kind::Module15Keys,
+            Kind::Module32Keys => streamdeck::Kind::Module32Keys,
+            Kind::Pedal => return None,
+        })
+    }
+
+    pub fn keys(self) -> u8 {
+        match self.streamdeck_kind() {
+            Some(kind) => kind.keys(),
+            None => 3,
+        }
+    }
+
+    /// Size of the image of a key, `(0, 0)` for devices without a display.
+    pub fn image_size(self) -> (usize, usize) {
+        self.streamdeck_kind()
+            .map_or((0, 0), |kind| kind.image_size())
+    }
+}
+
+impl From<streamdeck::Kind> for Kind {
+    fn from(kind: streamdeck::Kind) -> Self {
+        match kind {
+            streamdeck::Kind::Original => Kind::Original,
+            streamdeck::Kind::OriginalV2 => Kind::OriginalV2,
+            streamdeck::Kind::Mini => Kind::Mini,
+            streamdeck::Kind::RevisedMini => Kind::RevisedMini,
+            streamdeck::Kind::Xl => Kind::Xl,
+            streamdeck::Kind::Mk2 => Kind::Mk2,
+            streamdeck::Kind::Plus => Kind::Plus,
+            streamdeck::Kind::Module6Keys => Kind::Module6Keys,
+            streamdeck::Kind::Module15Keys => Kind::Module15Keys,
+            streamdeck::Kind::Module32Keys => Kind::Module32Keys,
+        }
+    }
+}
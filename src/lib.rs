@@ -1,4 +1,11 @@
-use std::time::Duration;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
 use bevy_app::{App, AppExit, Last, Plugin, PreStartup, PreUpdate};
 pub use bevy_color::{Color, ColorToComponents, LinearRgba};
@@ -10,131 +17,570 @@ use bevy_ecs::{
 #[cfg(feature = "image_compatibility")]
 use bevy_image::Image;
 use bevy_input::ButtonInput;
-use bevy_tasks::IoTaskPool;
-use crossbeam_channel::{Receiver, Sender, bounded};
+#[cfg(feature = "image_compatibility")]
+use bevy_math::URect;
+#[cfg(feature = "images")]
+use bevy_math::UVec2;
+use bevy_tasks::{IoTaskPool, futures_lite};
 #[cfg(feature = "images")]
-use image::{DynamicImage, ImageBuffer, Pixel, Rgba, imageops::FilterType};
+use image::DynamicImage;
+#[cfg(feature = "images")]
+use image::{Pixel, Rgba, imageops::FilterType};
 use log::debug;
-pub use streamdeck::Kind;
-use streamdeck::{Colour, Error};
+#[cfg(feature = "image_compatibility")]
+use log::warn;
+pub use streamdeck::{Colour, Error};
 
-pub struct StreamDeckPlugin;
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+mod animation;
+mod backend;
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+mod bindings;
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+mod camera;
+mod channel;
+mod chord;
+#[cfg(feature = "image_compatibility")]
+mod conversion;
+mod encoder;
+mod error;
+mod gesture;
+#[cfg(feature = "image_compatibility")]
+mod grid;
+mod idle;
+mod keys;
+mod kind;
+mod reconnect;
+#[cfg(feature = "text")]
+mod text;
+mod touchstrip;
+mod virtual_deck;
+
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+pub use animation::{AnimationMode, KeyAnimation};
+pub use backend::{
+    DeviceFilter, DeviceInput, ELGATO_VENDOR_ID, HidBackend, StreamDeckBackend,
+    StreamDeckConnection, encoders, has_display,
+};
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+pub use bindings::KeyImageBindings;
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+pub use camera::{DeckTarget, RenderToDeck};
+pub use channel::OverflowPolicy;
+use channel::{OrderQueue, PolicyReceiver};
+pub use chord::{Chord, ChordId, StreamDeckChord, StreamDeckChords};
+use chord::{ChordDetection, KeyTransition};
+#[cfg(feature = "image_compatibility")]
+pub use conversion::{ImageConversionError, image_to_dynamic};
+use encoder::EncoderInputs;
+pub use encoder::{EncoderRotation, StreamDeckEncoder};
+pub use error::{OrderError, OrderKind, StreamDeckError};
+pub use gesture::{GestureSettings, StreamDeckGesture};
+#[cfg(feature = "image_compatibility")]
+pub use grid::{GridMode, key_layout};
+pub use idle::IdlePolicy;
+#[cfg(all(feature = "assets", feature = "image_compatibility"))]
+pub use keys::KeyImage;
+pub use keys::{KeyColor, KeyPressed, StreamDeckKeyIndex};
+pub use kind::Kind;
+pub use reconnect::ReconnectStrategy;
+use reconnect::Scanner;
+#[cfg(feature = "text")]
+pub use text::{FontArc, HorizontalAlign, InvalidFont, TextStyle, VerticalAlign};
+pub use touchstrip::{TouchInput, touchstrip_size};
+pub use virtual_deck::{VirtualKey, VirtualOrder, VirtualStreamDeck};
+
+pub struct StreamDeckPlugin {
+    backend: Mutex<Option<Box<dyn StreamDeckBackend>>>,
+    filter: DeviceFilter,
+    reconnect: ReconnectStrategy,
+    settings: StreamDeckSettings,
+    idle_policy: Option<IdlePolicy>,
+}
+
+/// Settings of the IO task communicating with the devices.
+#[derive(Clone, Debug)]
+pub struct StreamDeckSettings {
+    /// How long to wait for key presses on each device before processing orders.
+    pub poll_interval: Duration,
+    /// Capacity of the channel carrying inputs from the devices.
+    pub event_channel_size: usize,
+    /// Capacity of the queue carrying orders to the devices. A new color or image for a key
+    /// replaces the one pending, so this is only reached when many keys are updated at once.
+    pub order_channel_size: usize,
+    /// What to do when a channel is full. Connections, disconnections and errors are never
+    /// discarded. Use [`OverflowPolicy::Grow`] to never discard orders either.
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for StreamDeckSettings {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_millis(1),
+            event_channel_size: 10,
+            order_channel_size: 100,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl Default for StreamDeckPlugin {
+    fn default() -> Self {
+        Self::with_backend(HidBackend)
+    }
+}
+
+impl StreamDeckPlugin {
+    /// Use a custom backend to connect to the Stream Deck.
+    pub fn with_backend(backend: impl StreamDeckBackend) -> Self {
+        Self {
+            backend: Mutex::new(Some(Box::new(backend))),
+            filter: DeviceFilter::default(),
+            reconnect: ReconnectStrategy::default(),
+            settings: StreamDeckSettings::default(),
+            idle_policy: None,
+        }
+    }
+
+    /// Dim devices when no key was pressed for a while.
+    pub fn with_idle_policy(mut self, idle_policy: IdlePolicy) -> Self {
+        self.idle_policy = Some(idle_policy);
+        self
+    }
+
+    pub fn with_settings(mut self, settings: StreamDeckSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Only connect to devices accepted by this filter.
+    pub fn with_filter(mut self, filter: DeviceFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Choose when to look for new devices.
+    pub fn with_reconnect_strategy(mut self, reconnect: ReconnectStrategy) -> Self {
+        self.reconnect = reconnect;
+        self
+    }
+}
 
 impl Plugin for StreamDeckPlugin {
     fn build(&self, app: &mut App) {
+        let backend = self
+            .backend
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| Box::new(HidBackend));
         app.add_message::<StreamDeckInput>()
+            .add_message::<StreamDeckError>()
+            .insert_resource(StreamDeckSetup {
+                backend: Mutex::new(Some(backend)),
+                filter: self.filter.clone(),
+                reconnect: self.reconnect.clone(),
+                settings: self.settings.clone(),
+            })
             .init_resource::<ButtonInput<StreamDeckKey>>()
+            .init_resource::<ButtonInput<StreamDeckEncoder>>()
+            .init_resource::<EncoderRotation>()
             .add_systems(PreStartup, listener)
             .add_systems(PreUpdate, receiver)
-            .add_systems(Last, exit_on_exit);
+            .add_systems(Last, exit_on_exit)
+            .add_plugins((chord::plugin, gesture::plugin, idle::plugin, keys::plugin));
+        if let Some(idle_policy) = self.idle_policy {
+            app.insert_resource(idle::IdleState::new(idle_policy));
+        }
+        #[cfg(all(feature = "assets", feature = "image_compatibility"))]
+        app.add_plugins((animation::plugin, bindings::plugin, camera::plugin));
+    }
+}
+
+/// Identifies a Stream Deck device.
+///
+/// Ids are given in the order devices are first connected, and are kept if a device is reconnected.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DeviceId(pub u8);
+
+/// A key on a Stream Deck device.
+///
+/// A key can be created from its index, in which case it is on the first device connected.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct StreamDeckKey {
+    pub device: DeviceId,
+    pub key: u8,
+}
+
+impl StreamDeckKey {
+    pub fn new(device: DeviceId, key: u8) -> Self {
+        Self { device, key }
     }
 }
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
-pub struct StreamDeckKey(pub u8);
+impl From<u8> for StreamDeckKey {
+    fn from(key: u8) -> Self {
+        Self::new(DeviceId::default(), key)
+    }
+}
+
+/// Information about a connected device.
+#[derive(Clone, Debug)]
+pub struct DeviceInfo {
+    pub kind: Kind,
+    pub serial: String,
+    pub firmware_version: Option<String>,
+    /// Whether images can be displayed on the touch strip. A Stream Deck+ that couldn't be opened
+    /// twice can't display them.
+    pub touchstrip: bool,
+}
 
 #[derive(Message, Debug)]
 pub enum StreamDeckInput {
-    Press(u8),
-    Release(u8),
-    Disconnected,
-    Connected(Kind),
+    Press(StreamDeckKey),
+    Release(StreamDeckKey),
+    EncoderPress(StreamDeckEncoder),
+    EncoderRelease(StreamDeckEncoder),
+    /// Ticks the encoder turned, positive clockwise.
+    EncoderTwist(StreamDeckEncoder, i8),
+    Touch(DeviceId, TouchInput),
+    Disconnected(DeviceId),
+    Connected(DeviceId, Kind),
 }
 
 #[derive(Debug)]
 enum StreamDeckEvent {
-    LostConnection,
-    Connected(streamdeck::Kind),
-    KeyPressed(Vec<u8>),
+    LostConnection(DeviceId),
+    Connected(DeviceId, DeviceInfo),
+    KeyPressed(DeviceId, Vec<u8>),
+    EncoderPressed(DeviceId, Vec<u8>),
+    EncoderTwisted(DeviceId, Vec<i8>),
+    Touched(DeviceId, TouchInput),
+    Error(StreamDeckError),
 }
 
+#[derive(Debug)]
 enum StreamDeckOrder {
-    Reset,
-    Color(u8, Color),
+    Reset(DeviceId),
+    Brightness(DeviceId, u8),
+    Color(StreamDeckKey, Color),
+    #[cfg(feature = "images")]
+    Image(StreamDeckKey, DynamicImage),
+    /// Image on the touch strip, with its top left corner at a position.
     #[cfg(feature = "images")]
-    Image(u8, DynamicImage),
+    Touchstrip(DeviceId, UVec2, DynamicImage),
+    Rescan,
     Exit,
 }
 
-fn listener(mut commands: Commands) {
-    let (event_tx, event_rx) = bounded::<StreamDeckEvent>(10);
-    let (order_tx, order_rx) = bounded::<StreamDeckOrder>(100);
+impl StreamDeckOrder {
+    fn device(&self) -> Option<DeviceId> {
+        match self {
+            StreamDeckOrder::Reset(device) | StreamDeckOrder::Brightness(device, _) => {
+                Some(*device)
+            }
+            StreamDeckOrder::Color(key, _) => Some(key.device),
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Image(key, _) => Some(key.device),
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Touchstrip(device, ..) => Some(*device),
+            StreamDeckOrder::Rescan | StreamDeckOrder::Exit => None,
+        }
+    }
+
+    fn kind(&self) -> Option<OrderKind> {
+        match self {
+            StreamDeckOrder::Reset(_) => Some(OrderKind::Reset),
+            StreamDeckOrder::Brightness(..) => Some(OrderKind::Brightness),
+            StreamDeckOrder::Color(..) => Some(OrderKind::Color),
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Image(..) => Some(OrderKind::Image),
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Touchstrip(..) => Some(OrderKind::Touchstrip),
+            StreamDeckOrder::Rescan | StreamDeckOrder::Exit => None,
+        }
+    }
+
+    fn key(&self) -> Option<u8> {
+        match self {
+            StreamDeckOrder::Color(key, _) => Some(key.key),
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Image(key, _) => Some(key.key),
+            _ => None,
+        }
+    }
+
+    fn image_hash(&self) -> Option<u64> {
+        match self {
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Image(_, image) => {
+                use std::hash::{DefaultHasher, Hash, Hasher};
+
+                let mut hasher = DefaultHasher::new();
+                (image.width(), image.height(), image.as_bytes()).hash(&mut hasher);
+                Some(hasher.finish())
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether this order makes a pending order obsolete.
+    fn replaces(&self, pending: &StreamDeckOrder) -> bool {
+        match (self, pending) {
+            // A reset clears the keys, but not the brightness
+            (StreamDeckOrder::Reset(_), StreamDeckOrder::Brightness(..)) => false,
+            (StreamDeckOrder::Reset(device), pending) => pending.device() == Some(*device),
+            (StreamDeckOrder::Brightness(device, _), StreamDeckOrder::Brightness(pending, _)) => {
+                device == pending
+            }
+            #[cfg(not(feature = "images"))]
+            (StreamDeckOrder::Color(key, _), StreamDeckOrder::Color(pending, _)) => key == pending,
+            #[cfg(feature = "images")]
+            (
+                StreamDeckOrder::Color(key, _) | StreamDeckOrder::Image(key, _),
+                StreamDeckOrder::Color(pending, _) | StreamDeckOrder::Image(pending, _),
+            ) => key == pending,
+            #[cfg(feature = "images")]
+            (
+                StreamDeckOrder::Touchstrip(device, position, image),
+                StreamDeckOrder::Touchstrip(pending, pending_position, pending_image),
+            ) => {
+                (device, position, image.width(), image.height())
+                    == (
+                        pending,
+                        pending_position,
+                        pending_image.width(),
+                        pending_image.height(),
+                    )
+            }
+            _ => false,
+        }
+    }
+
+    fn apply(self, streamdeck: &mut dyn StreamDeckConnection) -> Result<(), Error> {
+        match self {
+            StreamDeckOrder::Reset(_) => streamdeck.reset(),
+            StreamDeckOrder::Brightness(_, percent) => streamdeck.set_brightness(percent),
+            StreamDeckOrder::Color(key, color) => {
+                let [r, g, b] = device_rgb(color);
+                streamdeck.set_button_rgb(key.key + 1, &Colour { r, g, b })
+            }
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Image(key, image) => streamdeck.set_button_image(key.key + 1, image),
+            #[cfg(feature = "images")]
+            StreamDeckOrder::Touchstrip(_, position, image) => {
+                streamdeck.set_touchstrip_image(position, image)
+            }
+            StreamDeckOrder::Rescan | StreamDeckOrder::Exit => Ok(()),
+        }
+    }
+}
+
+struct ConnectedStreamDeck {
+    device: DeviceId,
+    connection: Box<dyn StreamDeckConnection>,
+    // Hash of the last image uploaded to each key
+    uploaded: HashMap<u8, u64>,
+}
+
+#[derive(Resource)]
+struct StreamDeckSetup {
+    backend: Mutex<Option<Box<dyn StreamDeckBackend>>>,
+    filter: DeviceFilter,
+    reconnect: ReconnectStrategy,
+    settings: StreamDeckSettings,
+}
+
+fn listener(mut commands: Commands, setup: Res<StreamDeckSetup>) {
+    let Some(mut backend) = setup.backend.lock().unwrap().take() else {
+        return;
+    };
+    commands.remove_resource::<StreamDeckSetup>();
+    let filter = setup.filter.clone();
+    let mut scanner = Scanner::new(setup.reconnect.clone());
+    let settings = setup.settings.clone();
+    let (event_tx, event_rx) =
+        channel::channel::<StreamDeckEvent>(settings.event_channel_size, settings.overflow_policy);
+    let orders = OrderQueue::new(settings.order_channel_size, settings.overflow_policy);
+    let order_rx = orders.clone();
+    let skipped_uploads = Arc::new(AtomicU64::new(0));
+    let skipped = skipped_uploads.clone();
 
     let taskpool = IoTaskPool::get();
     let task = taskpool.spawn(async move {
-        let mut streamdeck: Option<streamdeck::StreamDeck> = None;
-        loop {
-            let mut lost_connection = false;
-            if let Some(streamdeck) = streamdeck.as_mut() {
-                let mut act = || {
-                    let read = streamdeck.read_buttons(Some(Duration::from_millis(1)));
-                    match read {
-                        Ok(read) => {
-                            let _ = event_tx.send(StreamDeckEvent::KeyPressed(read));
-                        }
-                        Err(Error::NoData) => {}
-                        Err(err) => {
-                            return Err(err);
-                        }
+        let mut serials: Vec<String> = Vec::new();
+        let mut streamdecks: Vec<ConnectedStreamDeck> = Vec::new();
+        let mut last_connect_error: Option<String> = None;
+        'io: loop {
+            // The app was dropped without exiting
+            if event_tx.is_closed() {
+                break;
+            }
+            let mut lost_connections = Vec::new();
+            for ConnectedStreamDeck {
+                device, connection, ..
+            } in streamdecks.iter_mut()
+            {
+                match connection.read_input(Some(settings.poll_interval)) {
+                    Ok(DeviceInput::Buttons(read)) => {
+                        let _ = event_tx.send(StreamDeckEvent::KeyPressed(*device, read));
                     }
-
-                    for order in order_rx.try_iter() {
-                        match match order {
-                            StreamDeckOrder::Exit => return Ok(false),
-                            StreamDeckOrder::Reset => streamdeck.reset(),
-                            StreamDeckOrder::Color(k, color) => {
-                                let [r, g, b, _] = color.to_linear().to_f32_array();
-                                streamdeck.set_button_rgb(
-                                    k + 1,
-                                    &Colour {
-                                        r: (r * 255.0) as u8,
-                                        g: (g * 255.0) as u8,
-                                        b: (b * 255.0) as u8,
-                                    },
-                                )
-                            }
-                            #[cfg(feature = "images")]
-                            StreamDeckOrder::Image(k, image) => {
-                                streamdeck.set_button_image(k + 1, image)
-                            }
-                        } {
-                            Ok(_) => (),
-                            Err(Error::Hid(error)) => {
-                                debug!("HidError {:?}", error)
-                            }
-                            Err(err) => {
-                                return Err(err);
-                            }
-                        }
+                    Ok(DeviceInput::EncoderButtons(read)) => {
+                        let _ = event_tx.send(StreamDeckEvent::EncoderPressed(*device, read));
                     }
-                    Ok(true)
-                };
-                match act() {
-                    Ok(true) => (),
-                    Ok(false) => break,
+                    Ok(DeviceInput::EncoderTwist(read)) => {
+                        let _ = event_tx.send(StreamDeckEvent::EncoderTwisted(*device, read));
+                    }
+                    Ok(DeviceInput::Touch(touch)) => {
+                        let _ = event_tx.send(StreamDeckEvent::Touched(*device, touch));
+                    }
+                    Err(Error::NoData) => {}
                     Err(error) => {
                         debug!("Error communicating with StreamDeck: {:?}", error);
-                        let _ = event_tx.send(StreamDeckEvent::LostConnection);
-                        lost_connection = true;
+                        let _ = event_tx.send_unbounded(StreamDeckEvent::Error(StreamDeckError {
+                            device: Some(*device),
+                            key: None,
+                            order: OrderKind::ReadButtons,
+                            error,
+                        }));
+                        lost_connections.push(*device);
                     }
                 }
             }
-            if lost_connection {
-                streamdeck = None;
+
+            // Nothing to read, wait for orders instead of spinning
+            if streamdecks.is_empty() {
+                order_rx.wait(scanner.idle_wait());
             }
-            if streamdeck.is_none()
-                && let Ok(new_streamdeck) = streamdeck::StreamDeck::connect(0x0fd9, 0x0063, None)
-            {
-                let _ = event_tx.send(StreamDeckEvent::Connected(new_streamdeck.kind()));
-                streamdeck = Some(new_streamdeck);
+
+            for order in order_rx.drain() {
+                match order {
+                    StreamDeckOrder::Exit => break 'io,
+                    StreamDeckOrder::Rescan => scanner.rescan(),
+                    _ => (),
+                }
+                let (Some(device), Some(kind)) = (order.device(), order.kind()) else {
+                    continue;
+                };
+                let Some(streamdeck) = streamdecks
+                    .iter_mut()
+                    .find(|streamdeck| streamdeck.device == device)
+                else {
+                    continue;
+                };
+                let key = order.key();
+
+                // Skip images identical to the one already displayed
+                let image_hash = order.image_hash();
+                match (key, image_hash) {
+                    (Some(key), Some(hash)) if streamdeck.uploaded.get(&key) == Some(&hash) => {
+                        skipped_uploads.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                    (Some(key), _) => {
+                        streamdeck.uploaded.remove(&key);
+                    }
+                    (None, _) if matches!(order, StreamDeckOrder::Reset(_)) => {
+                        streamdeck.uploaded.clear();
+                    }
+                    // Brightness and touch strip orders don't change the keys
+                    (None, _) => (),
+                }
+
+                let result = order.apply(streamdeck.connection.as_mut());
+                if let (Ok(()), Some(key), Some(hash)) = (&result, key, image_hash) {
+                    streamdeck.uploaded.insert(key, hash);
+                }
+                if let Err(error) = result {
+                    debug!("Error communicating with StreamDeck: {:?}", error);
+                    // HID errors are not fatal, other errors mean the device is gone
+                    if !matches!(error, Error::Hid(_)) {
+                        lost_connections.push(device);
+                    }
+                    let _ = event_tx.send_unbounded(StreamDeckEvent::Error(StreamDeckError {
+                        device: Some(device),
+                        key,
+                        order: kind,
+                        error,
+                    }));
+                }
+            }
+
+            for device in lost_connections {
+                if let Some(index) = streamdecks
+                    .iter()
+                    .position(|streamdeck| streamdeck.device == device)
+                {
+                    streamdecks.remove(index);
+                    let _ = event_tx.send_unbounded(StreamDeckEvent::LostConnection(device));
+                    scanner.rescan();
+                }
+            }
+
+            if scanner.should_scan() {
+                let connected = streamdecks
+                    .iter()
+                    .map(|streamdeck| serials[streamdeck.device.0 as usize].clone())
+                    .collect::<Vec<_>>();
+                match backend.connect(&filter, &connected) {
+                    Ok(mut new_streamdeck) => {
+                        let serial = new_streamdeck.serial();
+                        let info = DeviceInfo {
+                            kind: new_streamdeck.kind(),
+                            serial: serial.clone(),
+                            firmware_version: new_streamdeck.firmware_version().ok(),
+                            touchstrip: new_streamdeck.has_touchstrip(),
+                        };
+                        let device = match serials.iter().position(|known| *known == serial) {
+                            Some(index) => DeviceId(index as u8),
+                            None => {
+                                serials.push(serial);
+                                DeviceId(serials.len() as u8 - 1)
+                            }
+                        };
+                        let _ = event_tx.send_unbounded(StreamDeckEvent::Connected(device, info));
+                        streamdecks.push(ConnectedStreamDeck {
+                            device,
+                            connection: new_streamdeck,
+                            uploaded: HashMap::new(),
+                        });
+                        last_connect_error = None;
+                        scanner.scanned(true);
+                    }
+                    // No device found
+                    Err(Error::NoData) => scanner.scanned(false),
+                    Err(error) => {
+                        // Report each error once, not on every scan
+                        let description = format!("{error:?}");
+                        if last_connect_error.as_ref() != Some(&description) {
+                            debug!("Error connecting to StreamDeck: {}", description);
+                            let _ =
+                                event_tx.send_unbounded(StreamDeckEvent::Error(StreamDeckError {
+                                    device: None,
+                                    key: None,
+                                    order: OrderKind::Connect,
+                                    error,
+                                }));
+                            last_connect_error = Some(description);
+                        }
+                        scanner.scanned(false);
+                    }
+                }
             }
+
+            // Let other IO tasks run, like those of other apps, as they share a few threads
+            futures_lite::future::yield_now().await;
         }
+        order_rx.close();
     });
     task.detach();
     commands.insert_resource(StreamDeckInternal { events: event_rx });
     commands.insert_resource(StreamDeck {
-        orders: order_tx,
-        kind: None,
+        orders,
+        devices: BTreeMap::new(),
+        keys: Mutex::new(HashMap::new()),
+        brightness: Mutex::new(HashMap::new()),
+        skipped_uploads: skipped,
     });
 }
 
@@ -142,29 +588,105 @@ fn receiver(
     mut streamdeck: ResMut<StreamDeck>,
     internal: Res<StreamDeckInternal>,
     mut inputs: ResMut<ButtonInput<StreamDeckKey>>,
+    encoders: EncoderInputs,
+    chords: ChordDetection,
     mut input_events: MessageWriter<StreamDeckInput>,
+    mut errors: MessageWriter<StreamDeckError>,
 ) {
+    let EncoderInputs {
+        buttons: mut encoder_inputs,
+        mut rotation,
+    } = encoders;
+    let ChordDetection {
+        mut chords,
+        time,
+        triggered: mut chord_events,
+    } = chords;
     inputs.clear();
+    encoder_inputs.clear();
+    rotation.clear_delta();
+    let now = time.map(|time| time.elapsed());
+    let mut transitions = Vec::new();
+    let mut triggered = Vec::new();
+    if let Some(now) = now {
+        chords.expire(now, &mut transitions);
+    }
+    send_key_transitions(&mut transitions, &mut inputs, &mut input_events);
     for from_stream in internal.events.try_iter() {
         match from_stream {
-            StreamDeckEvent::LostConnection => {
-                streamdeck.kind = None;
-                input_events.write(StreamDeckInput::Disconnected);
+            StreamDeckEvent::LostConnection(device) => {
+                streamdeck.devices.remove(&device);
+                let pressed = inputs
+                    .get_pressed()
+                    .filter(|key| key.device == device)
+                    .copied()
+                    .collect::<Vec<_>>();
+                for key in pressed {
+                    inputs.release(key);
+                    input_events.write(StreamDeckInput::Release(key));
+                }
+                let pressed = encoder_inputs
+                    .get_pressed()
+                    .filter(|encoder| encoder.device == device)
+                    .copied()
+                    .collect::<Vec<_>>();
+                for encoder in pressed {
+                    encoder_inputs.release(encoder);
+                    input_events.write(StreamDeckInput::EncoderRelease(encoder));
+                }
+                rotation.remove_device(device);
+                chords.remove_device(device);
+                input_events.write(StreamDeckInput::Disconnected(device));
             }
-            StreamDeckEvent::Connected(kind) => {
-                streamdeck.kind = Some(kind);
-                input_events.write(StreamDeckInput::Connected(kind));
+            StreamDeckEvent::Connected(device, info) => {
+                let kind = info.kind;
+                streamdeck.devices.insert(device, info);
+                streamdeck.redraw_device(device);
+                input_events.write(StreamDeckInput::Connected(device, kind));
             }
-            StreamDeckEvent::KeyPressed(keys) => {
+            StreamDeckEvent::Error(error) => {
+                errors.write(error);
+            }
+            StreamDeckEvent::KeyPressed(device, keys) => {
                 for (k, s) in keys.iter().enumerate() {
-                    if *s == 1 && !inputs.pressed(StreamDeckKey(k as u8)) {
-                        inputs.press(StreamDeckKey(k as u8));
-                        input_events.write(StreamDeckInput::Press(k as u8));
+                    let key = StreamDeckKey::new(device, k as u8);
+                    let held = inputs.pressed(key) || chords.is_hidden(key);
+                    if *s == 1 && !held {
+                        chords.press(key, now, &mut transitions, &mut triggered);
+                    }
+
+                    if *s == 0 && held {
+                        chords.release(key, &mut transitions);
+                    }
+                }
+                send_key_transitions(&mut transitions, &mut inputs, &mut input_events);
+                for chord in triggered.drain(..) {
+                    chord_events.write(StreamDeckChord(chord));
+                }
+            }
+            StreamDeckEvent::EncoderPressed(device, encoders) => {
+                for (e, s) in encoders.iter().enumerate() {
+                    let encoder = StreamDeckEncoder::new(device, e as u8);
+                    if *s == 1 && !encoder_inputs.pressed(encoder) {
+                        encoder_inputs.press(encoder);
+                        input_events.write(StreamDeckInput::EncoderPress(encoder));
                     }
 
-                    if *s == 0 && inputs.pressed(StreamDeckKey(k as u8)) {
-                        inputs.release(StreamDeckKey(k as u8));
-                        input_events.write(StreamDeckInput::Release(k as u8));
+                    if *s == 0 && encoder_inputs.pressed(encoder) {
+                        encoder_inputs.release(encoder);
+                        input_events.write(StreamDeckInput::EncoderRelease(encoder));
+                    }
+                }
+            }
+            StreamDeckEvent::Touched(device, touch) => {
+                input_events.write(StreamDeckInput::Touch(device, touch));
+            }
+            StreamDeckEvent::EncoderTwisted(device, ticks) => {
+                for (e, ticks) in ticks.into_iter().enumerate() {
+                    let encoder = StreamDeckEncoder::new(device, e as u8);
+                    if ticks != 0 {
+                        rotation.rotate(encoder, ticks);
+                        input_events.write(StreamDeckInput::EncoderTwist(encoder, ticks));
                     }
                 }
             }
@@ -172,105 +694,547 @@ fn receiver(
     }
 }
 
+fn send_key_transitions(
+    transitions: &mut Vec<KeyTransition>,
+    inputs: &mut ButtonInput<StreamDeckKey>,
+    input_events: &mut MessageWriter<StreamDeckInput>,
+) {
+    for transition in transitions.drain(..) {
+        match transition {
+            KeyTransition::Press(key) => {
+                inputs.press(key);
+                input_events.write(StreamDeckInput::Press(key));
+            }
+            KeyTransition::Release(key) => {
+                inputs.release(key);
+                input_events.write(StreamDeckInput::Release(key));
+            }
+        }
+    }
+}
+
 #[derive(Resource)]
 struct StreamDeckInternal {
-    events: Receiver<StreamDeckEvent>,
+    events: PolicyReceiver<StreamDeckEvent>,
 }
 
+/// Control the connected Stream Deck devices.
+///
+/// Methods taking a key accept either a [`StreamDeckKey`], or a key index on the first device connected.
+///
+/// Devices without a display, like the Pedal, are only used for input: orders sent to them fail with
+/// [`OrderError::Unsupported`].
 #[derive(Resource)]
 pub struct StreamDeck {
-    orders: Sender<StreamDeckOrder>,
-    kind: Option<Kind>,
+    orders: OrderQueue,
+    devices: BTreeMap<DeviceId, DeviceInfo>,
+    // Last content requested for each key, to redraw them when a device is reconnected
+    keys: Mutex<HashMap<StreamDeckKey, KeyContent>>,
+    brightness: Mutex<HashMap<DeviceId, u8>>,
+    skipped_uploads: Arc<AtomicU64>,
+}
+
+enum KeyContent {
+    Color(Color),
+    /// Processed for the kind of device it was first sent to.
+    #[cfg(feature = "images")]
+    Image(DynamicImage, Kind),
 }
 
 impl StreamDeck {
+    /// Kind of the first device connected, if it is currently connected.
     pub fn kind(&self) -> Option<Kind> {
-        self.kind
+        self.device_kind(DeviceId::default())
     }
 
-    pub fn set_key_color(&self, key: u8, color: Color) {
-        let _ = self.orders.send(StreamDeckOrder::Color(key, color));
+    pub fn device_kind(&self, device: DeviceId) -> Option<Kind> {
+        self.devices.get(&device).map(|info| info.kind)
     }
 
-    #[cfg(feature = "images")]
-    pub fn set_key_image(&self, key: u8, image: &Image) {
+    /// Serial of the first device connected, if it is currently connected.
+    pub fn serial(&self) -> Option<&str> {
+        self.device_info(DeviceId::default())
+            .map(|info| info.serial.as_str())
+    }
+
+    /// Firmware version of the first device connected, if it is currently connected.
+    pub fn firmware_version(&self) -> Option<&str> {
+        self.device_info(DeviceId::default())
+            .and_then(|info| info.firmware_version.as_deref())
+    }
+
+    pub fn device_info(&self, device: DeviceId) -> Option<&DeviceInfo> {
+        self.devices.get(&device)
+    }
+
+    /// Number of images not sent to a device because they were already displayed on the key.
+    pub fn skipped_uploads(&self) -> u64 {
+        self.skipped_uploads.load(Ordering::Relaxed)
+    }
+
+    /// Currently connected devices.
+    pub fn devices(&self) -> impl Iterator<Item = (DeviceId, Kind)> + '_ {
+        self.devices
+            .iter()
+            .map(|(device, info)| (*device, info.kind))
+    }
+
+    /// Kind of the device of the key, if the key exists on it.
+    fn key_kind(&self, key: StreamDeckKey) -> Result<Kind, OrderError> {
+        let kind = self.display_kind(key.device)?;
+        if key.key >= kind.keys() {
+            return Err(OrderError::KeyOutOfRange {
+                key,
+                keys: kind.keys(),
+            });
+        }
+        Ok(kind)
+    }
+
+    /// Kind of the device, if it has a display.
+    fn display_kind(&self, device: DeviceId) -> Result<Kind, OrderError> {
+        let kind = self
+            .device_kind(device)
+            .ok_or(OrderError::NotConnected(device))?;
+        if !has_display(kind) {
+            return Err(OrderError::Unsupported(device));
+        }
+        Ok(kind)
+    }
+
+    /// Connected devices with a display, failing if there are none.
+    fn display_devices(&self) -> Result<Vec<DeviceId>, OrderError> {
+        let Some((first, _)) = self.devices().next() else {
+            return Err(OrderError::NotConnected(DeviceId::default()));
+        };
+        let devices = self
+            .devices()
+            .filter(|(_, kind)| has_display(*kind))
+            .map(|(device, _)| device)
+            .collect::<Vec<_>>();
+        if devices.is_empty() {
+            return Err(OrderError::Unsupported(first));
+        }
+        Ok(devices)
+    }
+
+    fn send(&self, order: StreamDeckOrder) -> Result<(), OrderError> {
+        self.orders.send(order).map_err(|_| OrderError::QueueFull)
+    }
+
+    pub fn set_key_color(&self, key: impl Into<StreamDeckKey>, color: Color) {
+        let _ = self.try_set_key_color(key, color);
+    }
+
+    pub fn try_set_key_color(
+        &self,
+        key: impl Into<StreamDeckKey>,
+        color: Color,
+    ) -> Result<(), OrderError> {
+        let key = key.into();
+        self.key_kind(key)?;
+        self.send(StreamDeckOrder::Color(key, color))?;
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(key, KeyContent::Color(color));
+        Ok(())
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_key_image(&self, key: impl Into<StreamDeckKey>, image: &Image) {
         self.set_key_image_with_mode(key, image, ImageMode::default())
     }
 
-    #[cfg(feature = "images")]
-    pub fn set_key_image_with_mode(&self, key: u8, image: &Image, image_mode: ImageMode) {
-        if let Some(kind) = self.kind {
-            // Convert the texture to an image
-            let mut dynamic_image = match image.texture_descriptor.format {
-                bevy_render::render_resource::TextureFormat::Rgba8UnormSrgb => {
-                    ImageBuffer::from_raw(
-                        image.texture_descriptor.size.width,
-                        image.texture_descriptor.size.height,
-                        image.data.as_ref().unwrap().clone(),
-                    )
-                    .map(DynamicImage::ImageRgba8)
-                }
-                _ => unimplemented!(),
-            }
-            .unwrap();
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_key_image(
+        &self,
+        key: impl Into<StreamDeckKey>,
+        image: &Image,
+    ) -> Result<(), OrderError> {
+        self.try_set_key_image_with_mode(key, image, ImageMode::default())
+    }
 
-            // Resize the image to the size supported by the Stream Deck
-            let (x, y) = kind.image_size();
-            dynamic_image = match image_mode.resize {
-                ImageResize::Exact => {
-                    dynamic_image.resize_exact(x as u32, y as u32, FilterType::Gaussian)
-                }
-                ImageResize::Aspect => {
-                    dynamic_image.resize(x as u32, y as u32, FilterType::Gaussian)
-                }
-                ImageResize::AspectFill => {
-                    dynamic_image.resize_to_fill(x as u32, y as u32, FilterType::Gaussian)
-                }
-            };
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_key_image_with_mode(
+        &self,
+        key: impl Into<StreamDeckKey>,
+        image: &Image,
+        image_mode: ImageMode,
+    ) {
+        let key = key.into();
+        if let Err(OrderError::UnsupportedImage(error)) =
+            self.try_set_key_image_with_mode(key, image, image_mode)
+        {
+            warn!("Can't display image on key {}: {}", key.key, error);
+        }
+    }
 
-            // Apply a background
-            if let Some(background) = image_mode.background {
-                let bevy_color::LinearRgba {
-                    red, green, blue, ..
-                } = background.to_linear();
-
-                for pixel in dynamic_image.as_mut_rgba8().unwrap().pixels_mut() {
-                    pixel.blend(&Rgba([
-                        (red * 255.0) as u8,
-                        (green * 255.0) as u8,
-                        (blue * 255.0) as u8,
-                        255 - pixel.0[3],
-                    ]));
-                }
-            }
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_key_image_with_mode(
+        &self,
+        key: impl Into<StreamDeckKey>,
+        image: &Image,
+        image_mode: ImageMode,
+    ) -> Result<(), OrderError> {
+        let key = key.into();
+        self.key_kind(key)?;
+        // Convert the texture to an image
+        self.set_key_dynamic_image(key, image_to_dynamic(image)?, &image_mode)
+    }
 
-            // Invert
-            if image_mode.invert {
-                dynamic_image.invert();
-            }
+    /// Resize an image to the size supported by the key's device, and send it.
+    #[cfg(feature = "image_compatibility")]
+    fn set_key_dynamic_image(
+        &self,
+        key: StreamDeckKey,
+        image: DynamicImage,
+        image_mode: &ImageMode,
+    ) -> Result<(), OrderError> {
+        let (x, y) = self.key_kind(key)?.image_size();
+        self.send_image(key, process_image((x as u32, y as u32), image, image_mode))
+    }
+
+    /// Display one image across all keys of a device.
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_device_image(&self, device: DeviceId, image: &Image) {
+        self.set_device_image_with_mode(device, image, GridMode::default())
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_device_image(&self, device: DeviceId, image: &Image) -> Result<(), OrderError> {
+        self.try_set_device_image_with_mode(device, image, GridMode::default())
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_device_image_with_mode(&self, device: DeviceId, image: &Image, grid_mode: GridMode) {
+        if let Err(OrderError::UnsupportedImage(error)) =
+            self.try_set_device_image_with_mode(device, image, grid_mode)
+        {
+            warn!("Can't display image on device {}: {}", device.0, error);
+        }
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_device_image_with_mode(
+        &self,
+        device: DeviceId,
+        image: &Image,
+        grid_mode: GridMode,
+    ) -> Result<(), OrderError> {
+        let kind = self.display_kind(device)?;
+        let tiles = grid::slice_image(kind, image_to_dynamic(image)?, &grid_mode);
+        for (key, tile) in tiles.into_iter().enumerate() {
+            self.send_image(StreamDeckKey::new(device, key as u8), tile)?;
+        }
+        Ok(())
+    }
 
-            let _ = self.orders.send(StreamDeckOrder::Image(key, dynamic_image));
+    /// Display an image on the whole touch strip of a device.
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_touchstrip_image(&self, device: DeviceId, image: &Image) {
+        self.set_touchstrip_image_with_mode(device, image, ImageMode::default())
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_touchstrip_image(
+        &self,
+        device: DeviceId,
+        image: &Image,
+    ) -> Result<(), OrderError> {
+        self.try_set_touchstrip_image_with_mode(device, image, ImageMode::default())
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_touchstrip_image_with_mode(
+        &self,
+        device: DeviceId,
+        image: &Image,
+        image_mode: ImageMode,
+    ) {
+        if let Err(OrderError::UnsupportedImage(error)) =
+            self.try_set_touchstrip_image_with_mode(device, image, image_mode)
+        {
+            warn!(
+                "Can't display image on touch strip of device {}: {}",
+                device.0, error
+            );
         }
     }
 
-    pub fn reset_key(&self, key: u8) {
-        let _ = self.orders.send(StreamDeckOrder::Color(key, Color::BLACK));
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_touchstrip_image_with_mode(
+        &self,
+        device: DeviceId,
+        image: &Image,
+        image_mode: ImageMode,
+    ) -> Result<(), OrderError> {
+        let size = self.touchstrip_size(device)?;
+        self.try_set_touchstrip_region(
+            device,
+            URect::from_corners(UVec2::ZERO, size),
+            image,
+            image_mode,
+        )
     }
 
+    /// Display an image on a region of the touch strip of a device, leaving the rest unchanged.
+    #[cfg(feature = "image_compatibility")]
+    pub fn set_touchstrip_region(
+        &self,
+        device: DeviceId,
+        region: URect,
+        image: &Image,
+        image_mode: ImageMode,
+    ) {
+        if let Err(OrderError::UnsupportedImage(error)) =
+            self.try_set_touchstrip_region(device, region, image, image_mode)
+        {
+            warn!(
+                "Can't display image on touch strip of device {}: {}",
+                device.0, error
+            );
+        }
+    }
+
+    #[cfg(feature = "image_compatibility")]
+    pub fn try_set_touchstrip_region(
+        &self,
+        device: DeviceId,
+        region: URect,
+        image: &Image,
+        image_mode: ImageMode,
+    ) -> Result<(), OrderError> {
+        let size = self.touchstrip_size(device)?;
+        if region.is_empty() || region.max.x > size.x || region.max.y > size.y {
+            return Err(OrderError::RegionOutOfRange { region, size });
+        }
+        let image = process_image(
+            (region.width(), region.height()),
+            image_to_dynamic(image)?,
+            &image_mode,
+        );
+        self.send(StreamDeckOrder::Touchstrip(device, region.min, image))
+    }
+
+    /// Size of the touch strip of the device, if it has one.
+    #[cfg(feature = "image_compatibility")]
+    fn touchstrip_size(&self, device: DeviceId) -> Result<UVec2, OrderError> {
+        let info = self
+            .device_info(device)
+            .ok_or(OrderError::NotConnected(device))?;
+        touchstrip_size(info.kind)
+            .filter(|_| info.touchstrip)
+            .ok_or(OrderError::Unsupported(device))
+    }
+
+    /// Draw text on a key.
+    #[cfg(feature = "text")]
+    pub fn set_key_text(&self, key: impl Into<StreamDeckKey>, text: &str, style: &TextStyle) {
+        let _ = self.try_set_key_text(key, text, style);
+    }
+
+    #[cfg(feature = "text")]
+    pub fn try_set_key_text(
+        &self,
+        key: impl Into<StreamDeckKey>,
+        text: &str,
+        style: &TextStyle,
+    ) -> Result<(), OrderError> {
+        let key = key.into();
+        let kind = self.key_kind(key)?;
+        self.send_image(key, text::render_text(kind, text, style))
+    }
+
+    /// Send an image already processed for the key's device.
+    #[cfg(any(feature = "image_compatibility", feature = "text"))]
+    fn send_image(&self, key: StreamDeckKey, image: DynamicImage) -> Result<(), OrderError> {
+        let kind = self.key_kind(key)?;
+        self.send(StreamDeckOrder::Image(key, image.clone()))?;
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(key, KeyContent::Image(image, kind));
+        Ok(())
+    }
+
+    pub fn reset_key(&self, key: impl Into<StreamDeckKey>) {
+        let _ = self.try_reset_key(key);
+    }
+
+    pub fn try_reset_key(&self, key: impl Into<StreamDeckKey>) -> Result<(), OrderError> {
+        let key = key.into();
+        self.key_kind(key)?;
+        self.send(StreamDeckOrder::Color(key, Color::BLACK))?;
+        self.keys.lock().unwrap().remove(&key);
+        Ok(())
+    }
+
+    /// Reset all connected devices.
     pub fn reset(&self) {
-        let _ = self.orders.send(StreamDeckOrder::Reset);
+        let _ = self.try_reset();
+    }
+
+    /// Reset all connected devices with a display. Fails if none is connected.
+    pub fn try_reset(&self) -> Result<(), OrderError> {
+        for device in self.display_devices()? {
+            self.try_reset_device(device)?;
+        }
+        Ok(())
+    }
+
+    pub fn reset_device(&self, device: DeviceId) {
+        let _ = self.try_reset_device(device);
+    }
+
+    pub fn try_reset_device(&self, device: DeviceId) -> Result<(), OrderError> {
+        self.display_kind(device)?;
+        self.send(StreamDeckOrder::Reset(device))?;
+        self.keys
+            .lock()
+            .unwrap()
+            .retain(|key, _| key.device != device);
+        Ok(())
+    }
+
+    /// Set the brightness of all connected devices, from 0 to 100.
+    pub fn set_brightness(&self, percent: u8) {
+        let _ = self.try_set_brightness(percent);
+    }
+
+    /// Set the brightness of all connected devices with a display, from 0 to 100. Fails if none is
+    /// connected.
+    pub fn try_set_brightness(&self, percent: u8) -> Result<(), OrderError> {
+        for device in self.display_devices()? {
+            self.try_set_device_brightness(device, percent)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_device_brightness(&self, device: DeviceId, percent: u8) {
+        let _ = self.try_set_device_brightness(device, percent);
+    }
+
+    pub fn try_set_device_brightness(
+        &self,
+        device: DeviceId,
+        percent: u8,
+    ) -> Result<(), OrderError> {
+        self.display_kind(device)?;
+        let percent = percent.min(100);
+        self.send(StreamDeckOrder::Brightness(device, percent))?;
+        self.brightness.lock().unwrap().insert(device, percent);
+        Ok(())
+    }
+
+    /// Last brightness set on the device, if any.
+    pub fn brightness(&self, device: DeviceId) -> Option<u8> {
+        self.brightness.lock().unwrap().get(&device).copied()
+    }
+
+    /// Change the brightness without remembering it, so that it can be restored.
+    fn dim_device(&self, device: DeviceId, percent: u8) -> Result<(), OrderError> {
+        self.display_kind(device)?;
+        self.send(StreamDeckOrder::Brightness(device, percent.min(100)))
+    }
+
+    /// Send again the last brightness and content of each key of a device.
+    fn redraw_device(&self, device: DeviceId) {
+        let Some(kind) = self.device_kind(device) else {
+            return;
+        };
+        if let Some(percent) = self.brightness(device) {
+            let _ = self
+                .orders
+                .send(StreamDeckOrder::Brightness(device, percent));
+        }
+        for (key, content) in self.keys.lock().unwrap().iter() {
+            if key.device != device || key.key >= kind.keys() {
+                continue;
+            }
+            let order = match content {
+                KeyContent::Color(color) => StreamDeckOrder::Color(*key, *color),
+                #[cfg(feature = "images")]
+                KeyContent::Image(image, previous_kind) => {
+                    // The device may have been replaced by another kind with the same serial
+                    let (x, y) = kind.image_size();
+                    let (previous_x, previous_y) = previous_kind.image_size();
+                    if (previous_x, previous_y) == (x, y) {
+                        StreamDeckOrder::Image(*key, image.clone())
+                    } else {
+                        // Scaled like the key, as the image may be smaller than the key
+                        let width = (image.width() as usize * x / previous_x).max(1);
+                        let height = (image.height() as usize * y / previous_y).max(1);
+                        StreamDeckOrder::Image(
+                            *key,
+                            image.resize_exact(width as u32, height as u32, FilterType::Gaussian),
+                        )
+                    }
+                }
+            };
+            let _ = self.orders.send(order);
+        }
+    }
+
+    /// Look for new devices now, without waiting for the [`ReconnectStrategy`].
+    pub fn rescan(&self) {
+        let _ = self.orders.send(StreamDeckOrder::Rescan);
     }
 }
 
 fn exit_on_exit(streamdeck: Res<StreamDeck>, mut exit_events: MessageReader<AppExit>) {
     if exit_events.read().next().is_some() {
-        let _ = streamdeck.orders.send(StreamDeckOrder::Reset);
+        streamdeck.reset();
         let _ = streamdeck.orders.send(StreamDeckOrder::Exit);
     }
 }
 
+/// Red, green and blue components of a color, as displayed by the device.
+fn device_rgb(color: Color) -> [u8; 3] {
+    let [red, green, blue, _] = color.to_linear().to_f32_array();
+    [
+        (red * 255.0) as u8,
+        (green * 255.0) as u8,
+        (blue * 255.0) as u8,
+    ]
+}
+
+/// Resize to `(x, y)` and apply the image mode.
+#[cfg(feature = "images")]
+fn process_image(
+    (x, y): (u32, u32),
+    mut dynamic_image: DynamicImage,
+    image_mode: &ImageMode,
+) -> DynamicImage {
+    dynamic_image = match image_mode.resize {
+        ImageResize::Exact => dynamic_image.resize_exact(x, y, FilterType::Gaussian),
+        ImageResize::Aspect => dynamic_image.resize(x, y, FilterType::Gaussian),
+        ImageResize::AspectFill => dynamic_image.resize_to_fill(x, y, FilterType::Gaussian),
+    };
+
+    // Apply a background
+    if let Some(background) = image_mode.background {
+        // Images without an alpha channel, like RGB8 or Luma frames, can't be blended in place
+        if dynamic_image.as_rgba8().is_none() {
+            dynamic_image = DynamicImage::ImageRgba8(dynamic_image.to_rgba8());
+        }
+        let [red, green, blue] = device_rgb(background);
+
+        for pixel in dynamic_image.as_mut_rgba8().unwrap().pixels_mut() {
+            pixel.blend(&Rgba([red, green, blue, 255 - pixel.0[3]]));
+        }
+    }
+
+    // Invert
+    if image_mode.invert {
+        dynamic_image.invert();
+    }
+
+    dynamic_image
+}
+
 #[cfg(feature = "images")]
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum ImageResize {
     /// Does not preserve aspect ratio.
     #[default]
@@ -285,7 +1249,7 @@ pub enum ImageResize {
 }
 
 #[cfg(feature = "images")]
-#[derive(Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct ImageMode {
     pub resize: ImageResize,
     pub invert: bool,
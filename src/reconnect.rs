@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+#[cfg(all(target_os = "linux", feature = "hotplug"))]
+use crossbeam_channel::{Receiver, TrySendError, bounded};
+
+/// How often to look for new devices while no device is connected.
+const IDLE_WAIT: Duration = Duration::from_millis(50);
+
+/// When to look for new devices.
+///
+/// A scan can also be requested at any time with [`StreamDeck::rescan`](crate::StreamDeck::rescan).
+#[derive(Clone, Debug)]
+pub enum ReconnectStrategy {
+    /// Scan at a fixed interval.
+    Interval(Duration),
+    /// Scan at an interval that doubles after each scan without finding a new device, up to `max`.
+    ExponentialBackoff { initial: Duration, max: Duration },
+    /// Scan when a device is plugged in, as notified by udev.
+    #[cfg(all(target_os = "linux", feature = "hotplug"))]
+    Hotplug,
+}
+
+impl Default for ReconnectStrategy {
+    fn default() -> Self {
+        ReconnectStrategy::Interval(Duration::from_secs(1))
+    }
+}
+
+pub(crate) struct Scanner {
+    strategy: ReconnectStrategy,
+    next_scan: Instant,
+    backoff: Duration,
+    #[cfg(all(target_os = "linux", feature = "hotplug"))]
+    hotplug: Option<Receiver<()>>,
+}
+
+impl Scanner {
+    pub(crate) fn new(strategy: ReconnectStrategy) -> Self {
+        Self {
+            #[cfg(all(target_os = "linux", feature = "hotplug"))]
+            hotplug: matches!(strategy, ReconnectStrategy::Hotplug).then(hotplug_notifications),
+            backoff: strategy.initial_interval(),
+            strategy,
+            next_scan: Instant::now(),
+        }
+    }
+
+    /// Scan as soon as possible.
+    pub(crate) fn rescan(&mut self) {
+        self.next_scan = Instant::now();
+        self.backoff = self.strategy.initial_interval();
+    }
+
+    pub(crate) fn should_scan(&mut self) -> bool {
+        #[cfg(all(target_os = "linux", feature = "hotplug"))]
+        if let Some(hotplug) = self.hotplug.as_ref()
+            && hotplug.try_recv().is_ok()
+        {
+            self.rescan();
+        }
+        Instant::now() >= self.next_scan
+    }
+
+    /// Schedule the next scan. If a device was found, scan again right away as there may be others.
+    pub(crate) fn scanned(&mut self, found: bool) {
+        if found {
+            self.rescan();
+            return;
+        }
+        let interval = match &self.strategy {
+            ReconnectStrategy::Interval(interval) => *interval,
+            ReconnectStrategy::ExponentialBackoff { max, .. } => {
+                let interval = self.backoff;
+                self.backoff = (self.backoff * 2).min(*max);
+                interval
+            }
+            #[cfg(all(target_os = "linux", feature = "hotplug"))]
+            ReconnectStrategy::Hotplug => Duration::from_secs(3600),
+        };
+        self.next_scan = Instant::now() + interval;
+    }
+
+    /// How long to wait for orders when no device is connected.
+    pub(crate) fn idle_wait(&self) -> Duration {
+        self.next_scan
+            .saturating_duration_since(Instant::now())
+            .min(IDLE_WAIT)
+    }
+}
+
+impl ReconnectStrategy {
+    fn initial_interval(&self) -> Duration {
+        match self {
+            ReconnectStrategy::Interval(interval) => *interval,
+            ReconnectStrategy::ExponentialBackoff { initial, .. } => *initial,
+            #[cfg(all(target_os = "linux", feature = "hotplug"))]
+            ReconnectStrategy::Hotplug => Duration::ZERO,
+        }
+    }
+}
+
+/// Listen to udev for hidraw devices being added or removed.
+#[cfg(all(target_os = "linux", feature = "hotplug"))]
+fn hotplug_notifications() -> Receiver<()> {
+    let (notify_tx, notify_rx) = bounded(1);
+    std::thread::spawn(move || {
+        let socket = match udev::MonitorBuilder::new()
+            .and_then(|builder| builder.match_subsystem("hidraw"))
+            .and_then(|builder| builder.listen())
+        {
+            Ok(socket) => socket,
+            Err(error) => {
+                log::debug!("Error listening to udev: {:?}", error);
+                return;
+            }
+        };
+        loop {
+            if socket.iter().next().is_some() {
+                if let Err(TrySendError::Disconnected(_)) = notify_tx.try_send(()) {
+                    return;
+                }
+            } else {
+                std::thread::sleep(IDLE_WAIT);
+            }
+        }
+    });
+    notify_rx
+}
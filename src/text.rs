@@ -0,0 +1,179 @@
+use ab_glyph::{Font, GlyphId, PxScale, ScaleFont, point};
+pub use ab_glyph::{FontArc, InvalidFont};
+use bevy_color::Color;
+use image::{DynamicImage, Pixel, Rgba, RgbaImage, imageops, imageops::FilterType};
+
+use crate::{Kind, device_rgb};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HorizontalAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+    Top,
+    #[default]
+    Center,
+    Bottom,
+}
+
+/// How text is drawn on a key. Lines are separated by `\n`.
+#[derive(Clone, Debug)]
+pub struct TextStyle {
+    pub font: FontArc,
+    /// Height of a line, in pixels.
+    pub size: f32,
+    pub color: Color,
+    pub horizontal: HorizontalAlign,
+    pub vertical: VerticalAlign,
+    pub background: Option<Color>,
+    /// Drawn behind the text, resized to fit the key while preserving its aspect ratio.
+    pub icon: Option<DynamicImage>,
+}
+
+impl TextStyle {
+    /// White text, 24 pixels high, centered on a black key.
+    pub fn new(font: FontArc) -> Self {
+        Self {
+            font,
+            size: 24.0,
+            color: Color::WHITE,
+            horizontal: HorizontalAlign::default(),
+            vertical: VerticalAlign::default(),
+            background: None,
+            icon: None,
+        }
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_alignment(mut self, horizontal: HorizontalAlign, vertical: VerticalAlign) -> Self {
+        self.horizontal = horizontal;
+        self.vertical = vertical;
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn with_icon(mut self, icon: DynamicImage) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+}
+
+/// Rasterize text to an image the size of a key of this kind of device.
+pub(crate) fn render_text(kind: Kind, text: &str, style: &TextStyle) -> DynamicImage {
+    let (width, height) = kind.image_size();
+    let (width, height) = (width as u32, height as u32);
+    let mut canvas = RgbaImage::from_pixel(
+        width,
+        height,
+        to_rgba(style.background.unwrap_or(Color::BLACK)),
+    );
+
+    if let Some(icon) = &style.icon {
+        let icon = icon.resize(width, height, FilterType::Gaussian).to_rgba8();
+        imageops::overlay(
+            &mut canvas,
+            &icon,
+            ((width - icon.width()) / 2) as i64,
+            ((height - icon.height()) / 2) as i64,
+        );
+    }
+
+    let font = style.font.as_scaled(PxScale::from(style.size));
+    let Rgba([red, green, blue, _]) = to_rgba(style.color);
+    let lines: Vec<&str> = text.lines().collect();
+    let line_height = font.height() + font.line_gap();
+    let text_height = line_height * lines.len() as f32 - font.line_gap();
+    let top = match style.vertical {
+        VerticalAlign::Top => 0.0,
+        VerticalAlign::Center => (height as f32 - text_height) / 2.0,
+        VerticalAlign::Bottom => height as f32 - text_height,
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        let line_width = glyphs(&font, line).last().map_or(0.0, |(_, end)| end);
+        let left = match style.horizontal {
+            HorizontalAlign::Left => 0.0,
+            HorizontalAlign::Center => (width as f32 - line_width) / 2.0,
+            HorizontalAlign::Right => width as f32 - line_width,
+        };
+        let baseline = top + line_height * i as f32 + font.ascent();
+
+        for (id, end) in glyphs(&font, line) {
+            let glyph = id.with_scale_and_position(
+                font.scale(),
+                point(left + end - font.h_advance(id), baseline),
+            );
+            if let Some(outlined) = font.outline_glyph(glyph) {
+                let bounds = outlined.px_bounds();
+                outlined.draw(|x, y, coverage| {
+                    let x = bounds.min.x as i32 + x as i32;
+                    let y = bounds.min.y as i32 + y as i32;
+                    if x >= 0 && y >= 0 && (x as u32) < width && (y as u32) < height {
+                        canvas.get_pixel_mut(x as u32, y as u32).blend(&Rgba([
+                            red,
+                            green,
+                            blue,
+                            (coverage.clamp(0.0, 1.0) * 255.0) as u8,
+                        ]));
+                    }
+                });
+            }
+        }
+    }
+
+    DynamicImage::ImageRgba8(canvas)
+}
+
+/// Glyphs of a line, with the position where each one ends, kerning included.
+fn glyphs<'a, F: Font>(
+    font: &'a impl ScaleFont<F>,
+    line: &'a str,
+) -> impl Iterator<Item = (GlyphId, f32)> + 'a {
+    let mut previous: Option<GlyphId> = None;
+    let mut caret = 0.0;
+    line.chars().map(move |c| {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            caret += font.kern(previous, id);
+        }
+        caret += font.h_advance(id);
+        previous = Some(id);
+        (id, caret)
+    })
+}
+
+fn to_rgba(color: Color) -> Rgba<u8> {
+    let [red, green, blue] = device_rgb(color);
+    Rgba([red, green, blue, 255])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_text_on_a_key_sized_image() {
+        let font = FontArc::try_from_slice(bevy::text::DEFAULT_FONT_DATA).unwrap();
+        let image = render_text(Kind::Mini, "Ab\n12", &TextStyle::new(font)).to_rgba8();
+        assert_eq!(image.dimensions(), (80, 80));
+        assert!(image.pixels().any(|pixel| pixel.0 != [0, 0, 0, 255]));
+    }
+}
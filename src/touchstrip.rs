@@ -0,0 +1,37 @@
+use bevy_math::{IVec2, UVec2};
+
+use crate::Kind;
+
+/// A touch on the touch strip, in pixels from its top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TouchInput {
+    Tap(UVec2),
+    LongPress(UVec2),
+    Swipe { start: UVec2, end: UVec2 },
+}
+
+impl TouchInput {
+    /// Where the touch started.
+    pub fn position(&self) -> UVec2 {
+        match self {
+            TouchInput::Tap(position) | TouchInput::LongPress(position) => *position,
+            TouchInput::Swipe { start, .. } => *start,
+        }
+    }
+
+    /// From the start to the end of a swipe.
+    pub fn swipe_vector(&self) -> Option<IVec2> {
+        match self {
+            TouchInput::Swipe { start, end } => Some(end.as_ivec2() - start.as_ivec2()),
+            _ => None,
+        }
+    }
+}
+
+/// Size of the touch strip of this kind of device, if it has one.
+pub fn touchstrip_size(kind: Kind) -> Option<UVec2> {
+    match kind {
+        Kind::Plus => Some(UVec2::new(800, 100)),
+        _ => None,
+    }
+}
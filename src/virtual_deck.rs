@@ -0,0 +1,349 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+#[cfg(feature = "images")]
+use bevy_math::UVec2;
+use hidapi::HidError;
+#[cfg(feature = "images")]
+use image::{DynamicImage, RgbaImage, imageops};
+use streamdeck::{Colour, Error};
+
+use crate::backend::{
+    DeviceFilter, DeviceInput, StreamDeckBackend, StreamDeckConnection, encoders,
+};
+#[cfg(feature = "images")]
+use crate::touchstrip_size;
+use crate::{Kind, TouchInput};
+
+/// An in-memory Stream Deck, for running without hardware.
+///
+/// Clones share the same device, so one can be given to [`StreamDeckPlugin::with_backend`](crate::StreamDeckPlugin::with_backend)
+/// while another is kept to press keys and inspect what is displayed.
+#[derive(Clone)]
+pub struct VirtualStreamDeck {
+    state: Arc<Mutex<VirtualState>>,
+}
+
+struct VirtualState {
+    kind: Kind,
+    serial: String,
+    plugged: bool,
+    buttons: Vec<u8>,
+    encoders: Vec<u8>,
+    inputs: VecDeque<DeviceInput>,
+    keys: Vec<VirtualKey>,
+    #[cfg(feature = "images")]
+    touchstrip: Option<RgbaImage>,
+    brightness: u8,
+    orders: Vec<VirtualOrder>,
+}
+
+/// What is displayed on a key of a [`VirtualStreamDeck`].
+#[derive(Clone, Debug, Default)]
+pub enum VirtualKey {
+    #[default]
+    Blank,
+    Color([u8; 3]),
+    #[cfg(feature = "images")]
+    Image(DynamicImage),
+}
+
+/// An order received by a [`VirtualStreamDeck`]. Keys start at 0.
+#[derive(Clone, Debug)]
+pub enum VirtualOrder {
+    Reset,
+    Brightness(u8),
+    Color(u8, [u8; 3]),
+    #[cfg(feature = "images")]
+    Image(u8, DynamicImage),
+    #[cfg(feature = "images")]
+    Touchstrip(UVec2, DynamicImage),
+}
+
+impl VirtualStreamDeck {
+    pub fn new(kind: Kind) -> Self {
+        Self::with_serial(kind, "VIRTUAL")
+    }
+
+    /// Create a virtual device with a serial. Devices need different serials to be connected at the same time.
+    pub fn with_serial(kind: Kind, serial: impl Into<String>) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(VirtualState {
+                kind,
+                serial: serial.into(),
+                plugged: true,
+                buttons: vec![0; kind.keys() as usize],
+                encoders: vec![0; encoders(kind) as usize],
+                inputs: VecDeque::new(),
+                keys: vec![VirtualKey::Blank; kind.keys() as usize],
+                #[cfg(feature = "images")]
+                touchstrip: touchstrip_size(kind).map(|size| RgbaImage::new(size.x, size.y)),
+                brightness: 100,
+                orders: Vec::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, VirtualState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.state().kind
+    }
+
+    pub fn serial(&self) -> String {
+        self.state().serial.clone()
+    }
+
+    /// Press a key. It will be read by the plugin as a [`StreamDeckInput::Press`](crate::StreamDeckInput::Press).
+    pub fn press(&self, key: u8) {
+        self.set_button(key, 1);
+    }
+
+    /// Release a key. It will be read by the plugin as a [`StreamDeckInput::Release`](crate::StreamDeckInput::Release).
+    pub fn release(&self, key: u8) {
+        self.set_button(key, 0);
+    }
+
+    fn set_button(&self, key: u8, value: u8) {
+        let mut state = self.state();
+        if let Some(button) = state.buttons.get_mut(key as usize) {
+            *button = value;
+            let buttons = state.buttons.clone();
+            state.inputs.push_back(DeviceInput::Buttons(buttons));
+        }
+    }
+
+    /// Press an encoder. It will be read by the plugin as a [`StreamDeckInput::EncoderPress`](crate::StreamDeckInput::EncoderPress).
+    pub fn press_encoder(&self, encoder: u8) {
+        self.set_encoder(encoder, 1);
+    }
+
+    /// Release an encoder. It will be read by the plugin as a [`StreamDeckInput::EncoderRelease`](crate::StreamDeckInput::EncoderRelease).
+    pub fn release_encoder(&self, encoder: u8) {
+        self.set_encoder(encoder, 0);
+    }
+
+    fn set_encoder(&self, encoder: u8, value: u8) {
+        let mut state = self.state();
+        if let Some(button) = state.encoders.get_mut(encoder as usize) {
+            *button = value;
+            let encoders = state.encoders.clone();
+            state
+                .inputs
+                .push_back(DeviceInput::EncoderButtons(encoders));
+        }
+    }
+
+    /// Touch the touch strip. It will be read by the plugin as a [`StreamDeckInput::Touch`](crate::StreamDeckInput::Touch).
+    pub fn touch(&self, touch: TouchInput) {
+        self.state().inputs.push_back(DeviceInput::Touch(touch));
+    }
+
+    /// Turn an encoder, positive clockwise. It will be read by the plugin as a [`StreamDeckInput::EncoderTwist`](crate::StreamDeckInput::EncoderTwist).
+    pub fn twist_encoder(&self, encoder: u8, ticks: i8) {
+        let mut state = self.state();
+        if (encoder as usize) < state.encoders.len() {
+            let mut twist = vec![0; state.encoders.len()];
+            twist[encoder as usize] = ticks;
+            state.inputs.push_back(DeviceInput::EncoderTwist(twist));
+        }
+    }
+
+    /// Disconnect the device. Its keys are cleared, as on a real device losing power.
+    pub fn unplug(&self) {
+        let mut state = self.state();
+        state.plugged = false;
+        state.keys.fill(VirtualKey::Blank);
+        state.buttons.fill(0);
+        state.encoders.fill(0);
+        state.inputs.clear();
+        #[cfg(feature = "images")]
+        if let Some(touchstrip) = &mut state.touchstrip {
+            touchstrip.fill(0);
+        }
+    }
+
+    /// Connect the device again after [`VirtualStreamDeck::unplug`].
+    pub fn plug(&self) {
+        self.state().plugged = true;
+    }
+
+    /// Brightness of the screen, from 0 to 100.
+    pub fn brightness(&self) -> u8 {
+        self.state().brightness
+    }
+
+    /// What is currently displayed on a key.
+    pub fn key(&self, key: u8) -> VirtualKey {
+        self.state()
+            .keys
+            .get(key as usize)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// What is currently displayed on the touch strip, if the device has one.
+    #[cfg(feature = "images")]
+    pub fn touchstrip(&self) -> Option<DynamicImage> {
+        self.state()
+            .touchstrip
+            .clone()
+            .map(DynamicImage::ImageRgba8)
+    }
+
+    /// Every order received since the device was created or the orders were last cleared.
+    pub fn orders(&self) -> Vec<VirtualOrder> {
+        self.state().orders.clone()
+    }
+
+    pub fn clear_orders(&self) {
+        self.state().orders.clear();
+    }
+}
+
+impl StreamDeckBackend for VirtualStreamDeck {
+    fn connect(
+        &mut self,
+        filter: &DeviceFilter,
+        connected: &[String],
+    ) -> Result<Box<dyn StreamDeckConnection>, Error> {
+        let serial = self.serial();
+        if !self.state().plugged
+            || !filter.accepts_kind(self.kind())
+            || !filter.accepts_serial(&serial)
+            || connected.contains(&serial)
+        {
+            return Err(Error::NoData);
+        }
+        Ok(Box::new(self.clone()))
+    }
+}
+
+/// Several virtual devices, connected one after the other.
+impl StreamDeckBackend for Vec<VirtualStreamDeck> {
+    fn connect(
+        &mut self,
+        filter: &DeviceFilter,
+        connected: &[String],
+    ) -> Result<Box<dyn StreamDeckConnection>, Error> {
+        let mut streamdecks = self.iter_mut().collect::<Vec<_>>();
+        streamdecks.sort_by_key(|streamdeck| filter.serial_priority(&streamdeck.serial()));
+        streamdecks
+            .into_iter()
+            .find_map(|streamdeck| streamdeck.connect(filter, connected).ok())
+            .ok_or(Error::NoData)
+    }
+}
+
+impl StreamDeckConnection for VirtualStreamDeck {
+    fn kind(&self) -> Kind {
+        VirtualStreamDeck::kind(self)
+    }
+
+    fn serial(&self) -> String {
+        VirtualStreamDeck::serial(self)
+    }
+
+    fn firmware_version(&mut self) -> Result<String, Error> {
+        Ok(String::from("virtual"))
+    }
+
+    fn read_buttons(&mut self, timeout: Option<Duration>) -> Result<Vec<u8>, Error> {
+        match self.read_input(timeout)? {
+            DeviceInput::Buttons(buttons) => Ok(buttons),
+            _ => Err(Error::NoData),
+        }
+    }
+
+    fn read_input(&mut self, timeout: Option<Duration>) -> Result<DeviceInput, Error> {
+        let mut state = self.state();
+        if !state.plugged {
+            return Err(unplugged());
+        }
+        if let Some(input) = state.inputs.pop_front() {
+            return Ok(input);
+        }
+        drop(state);
+        if let Some(timeout) = timeout {
+            std::thread::sleep(timeout);
+        }
+        Err(Error::NoData)
+    }
+
+    fn set_button_rgb(&mut self, key: u8, colour: &Colour) -> Result<(), Error> {
+        let color = [colour.r, colour.g, colour.b];
+        let mut state = self.state();
+        if !state.plugged {
+            return Err(unplugged());
+        }
+        let key = key - 1;
+        if let Some(virtual_key) = state.keys.get_mut(key as usize) {
+            *virtual_key = VirtualKey::Color(color);
+        }
+        state.orders.push(VirtualOrder::Color(key, color));
+        Ok(())
+    }
+
+    #[cfg(feature = "images")]
+    fn set_button_image(&mut self, key: u8, image: DynamicImage) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.plugged {
+            return Err(unplugged());
+        }
+        let key = key - 1;
+        if let Some(virtual_key) = state.keys.get_mut(key as usize) {
+            *virtual_key = VirtualKey::Image(image.clone());
+        }
+        state.orders.push(VirtualOrder::Image(key, image));
+        Ok(())
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.plugged {
+            return Err(unplugged());
+        }
+        state.keys.fill(VirtualKey::Blank);
+        state.orders.push(VirtualOrder::Reset);
+        Ok(())
+    }
+
+    fn set_brightness(&mut self, percent: u8) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.plugged {
+            return Err(unplugged());
+        }
+        state.brightness = percent;
+        state.orders.push(VirtualOrder::Brightness(percent));
+        Ok(())
+    }
+
+    #[cfg(feature = "images")]
+    fn set_touchstrip_image(&mut self, position: UVec2, image: DynamicImage) -> Result<(), Error> {
+        let mut state = self.state();
+        if !state.plugged {
+            return Err(unplugged());
+        }
+        if let Some(touchstrip) = &mut state.touchstrip {
+            imageops::replace(
+                touchstrip,
+                &image.to_rgba8(),
+                position.x as i64,
+                position.y as i64,
+            );
+        }
+        state.orders.push(VirtualOrder::Touchstrip(position, image));
+        Ok(())
+    }
+}
+
+fn unplugged() -> Error {
+    Error::Hid(HidError::HidApiError {
+        message: String::from("virtual device unplugged"),
+    })
+}
@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_streamdeck::{
+    DeviceId, Kind, OrderError, StreamDeck, StreamDeckBackend, StreamDeckInput, StreamDeckKey,
+    StreamDeckPlugin, VirtualKey, VirtualStreamDeck,
+};
+
+#[derive(Resource, Default)]
+struct Received {
+    connected: Vec<Kind>,
+    pressed: Vec<StreamDeckKey>,
+    released: Vec<StreamDeckKey>,
+}
+
+fn record_inputs(mut inputs: MessageReader<StreamDeckInput>, mut received: ResMut<Received>) {
+    for input in inputs.read() {
+        match input {
+            StreamDeckInput::Connected(_, kind) => received.connected.push(*kind),
+            StreamDeckInput::Press(key) => received.pressed.push(*key),
+            StreamDeckInput::Release(key) => received.released.push(*key),
+            _ => (),
+        }
+    }
+}
+
+fn light_pressed_keys(streamdeck: Res<StreamDeck>, mut inputs: MessageReader<StreamDeckInput>) {
+    for input in inputs.read() {
+        match input {
+            StreamDeckInput::Press(key) => streamdeck.set_key_color(*key, Color::WHITE),
+            StreamDeckInput::Release(key) => streamdeck.reset_key(*key),
+            _ => (),
+        }
+    }
+}
+
+fn app(backend: impl StreamDeckBackend) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StreamDeckPlugin::with_backend(backend)))
+        .init_resource::<Received>()
+        .add_systems(Update, (record_inputs, light_pressed_keys));
+    app
+}
+
+/// The device is read and written in a background task, update the app until the condition holds.
+fn wait_for(app: &mut App, condition: impl Fn(&mut App) -> bool) {
+    for _ in 0..1000 {
+        app.update();
+        if condition(app) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    panic!("the virtual Stream Deck did not reach the expected state");
+}
+
+fn received(app: &App) -> &Received {
+    app.world().resource::<Received>()
+}
+
+fn streamdeck(app: &App) -> &StreamDeck {
+    app.world().resource::<StreamDeck>()
+}
+
+#[cfg(feature = "image_compatibility")]
+fn red_image(width: u32, height: u32) -> Image {
+    use bevy::{
+        asset::RenderAssetUsages,
+        render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    };
+
+    Image::new_fill(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[255, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+#[cfg(feature = "image_compatibility")]
+fn displayed_size(deck: &VirtualStreamDeck, key: u8) -> Option<(u32, u32)> {
+    match deck.key(key) {
+        VirtualKey::Image(image) => Some((image.width(), image.height())),
+        _ => None,
+    }
+}
+
+#[test]
+fn connects_and_reads_key_presses() {
+    let deck = VirtualStreamDeck::new(Kind::Mini);
+    let mut app = app(deck.clone());
+
+    wait_for(&mut app, |app| {
+        received(app)
+            .connected
+            .iter()
+            .any(|kind| matches!(kind, Kind::Mini))
+    });
+
+    deck.press(2);
+    wait_for(&mut app, |app| {
+        received(app).pressed.contains(&StreamDeckKey::from(2))
+    });
+    assert!(
+        app.world()
+            .resource::<ButtonInput<StreamDeckKey>>()
+            .pressed(StreamDeckKey::from(2))
+    );
+
+    deck.release(2);
+    wait_for(&mut app, |app| {
+        received(app).released.contains(&StreamDeckKey::from(2))
+    });
+}
+
+#[test]
+fn displays_colors_on_pressed_keys() {
+    let deck = VirtualStreamDeck::new(Kind::Mini);
+    let mut app = app(deck.clone());
+
+    deck.press(2);
+    wait_for(&mut app, |_| {
+        matches!(deck.key(2), VirtualKey::Color([255, 255, 255]))
+    });
+    assert!(matches!(
+        deck.key(1),
+        VirtualKey::Blank | VirtualKey::Color([0, 0, 0])
+    ));
+
+    deck.release(2);
+    wait_for(&mut app, |_| {
+        matches!(deck.key(2), VirtualKey::Color([0, 0, 0]))
+    });
+}
+
+#[test]
+fn pedal_is_only_used_for_input() {
+    let deck = VirtualStreamDeck::new(Kind::Pedal);
+    let mut app = app(deck.clone());
+
+    deck.press(1);
+    wait_for(&mut app, |app| {
+        received(app).pressed.contains(&StreamDeckKey::from(1))
+    });
+    assert_eq!(received(&app).connected, vec![Kind::Pedal]);
+    assert!(matches!(
+        app.world()
+            .resource::<StreamDeck>()
+            .try_set_key_color(1, Color::WHITE),
+        Err(OrderError::Unsupported(DeviceId(0)))
+    ));
+    assert!(deck.orders().is_empty());
+}
+
+#[cfg(feature = "image_compatibility")]
+#[test]
+fn displays_images_resized_to_the_key() {
+    let deck = VirtualStreamDeck::new(Kind::Mini);
+    let mut app = app(deck.clone());
+    wait_for(&mut app, |app| {
+        app.world().resource::<StreamDeck>().kind().is_some()
+    });
+
+    streamdeck(&app).set_key_image(0, &red_image(4, 4));
+
+    wait_for(&mut app, |_| matches!(deck.key(0), VirtualKey::Image(_)));
+    let VirtualKey::Image(displayed) = deck.key(0) else {
+        unreachable!();
+    };
+    let (width, height) = Kind::Mini.image_size();
+    assert_eq!(
+        (displayed.width(), displayed.height()),
+        (width as u32, height as u32)
+    );
+    let displayed = displayed.to_rgb8();
+    assert!(displayed.pixels().all(|pixel| pixel.0 == [255, 0, 0]));
+}
+
+#[cfg(feature = "image_compatibility")]
+#[test]
+fn redraws_keys_when_reconnected() {
+    let deck = VirtualStreamDeck::new(Kind::Mini);
+    let mut app = app(deck.clone());
+    wait_for(&mut app, |app| streamdeck(app).kind().is_some());
+
+    streamdeck(&app).set_key_color(1, Color::WHITE);
+    streamdeck(&app).set_key_image(0, &red_image(4, 4));
+    wait_for(&mut app, |_| {
+        matches!(deck.key(1), VirtualKey::Color([255, 255, 255]))
+            && displayed_size(&deck, 0).is_some()
+    });
+
+    deck.unplug();
+    wait_for(&mut app, |app| streamdeck(app).kind().is_none());
+    assert!(matches!(deck.key(0), VirtualKey::Blank));
+
+    deck.plug();
+    streamdeck(&app).rescan();
+    wait_for(&mut app, |_| {
+        matches!(deck.key(1), VirtualKey::Color([255, 255, 255]))
+            && displayed_size(&deck, 0).is_some()
+    });
+}
+
+#[cfg(feature = "image_compatibility")]
+#[test]
+fn redraws_images_resized_for_another_kind_with_the_same_serial() {
+    let mini = VirtualStreamDeck::with_serial(Kind::Mini, "SERIAL");
+    let mk2 = VirtualStreamDeck::with_serial(Kind::Mk2, "SERIAL");
+    mk2.unplug();
+    let mut app = app(vec![mini.clone(), mk2.clone()]);
+    wait_for(&mut app, |app| streamdeck(app).kind() == Some(Kind::Mini));
+
+    streamdeck(&app).set_key_image(0, &red_image(4, 4));
+    wait_for(&mut app, |_| displayed_size(&mini, 0).is_some());
+
+    mini.unplug();
+    wait_for(&mut app, |app| streamdeck(app).kind().is_none());
+    mk2.plug();
+    streamdeck(&app).rescan();
+    wait_for(&mut app, |app| streamdeck(app).kind() == Some(Kind::Mk2));
+
+    let (width, height) = Kind::Mk2.image_size();
+    wait_for(&mut app, |_| {
+        displayed_size(&mk2, 0) == Some((width as u32, height as u32))
+    });
+    assert_eq!(received(&app).connected, vec![Kind::Mini, Kind::Mk2]);
+}
+
+#[cfg(feature = "image_compatibility")]
+#[test]
+fn skips_uploading_the_image_already_displayed() {
+    use bevy_streamdeck::VirtualOrder;
+
+    let deck = VirtualStreamDeck::new(Kind::Mini);
+    let mut app = app(deck.clone());
+    wait_for(&mut app, |app| streamdeck(app).kind().is_some());
+    let image = red_image(4, 4);
+    let uploads = || {
+        deck.orders()
+            .iter()
+            .filter(|order| matches!(order, VirtualOrder::Image(0, _)))
+            .count()
+    };
+
+    streamdeck(&app).set_key_image(0, &image);
+    wait_for(&mut app, |_| uploads() == 1);
+    streamdeck(&app).set_key_image(0, &image);
+    wait_for(&mut app, |app| streamdeck(app).skipped_uploads() == 1);
+    assert_eq!(uploads(), 1);
+
+    // A color replaces the image, which must be uploaded again
+    streamdeck(&app).set_key_color(0, Color::WHITE);
+    wait_for(&mut app, |_| {
+        matches!(deck.key(0), VirtualKey::Color([255, 255, 255]))
+    });
+    streamdeck(&app).set_key_image(0, &image);
+    wait_for(&mut app, |_| uploads() == 2);
+
+    streamdeck(&app).reset_device(DeviceId(0));
+    wait_for(&mut app, |_| matches!(deck.key(0), VirtualKey::Blank));
+    streamdeck(&app).set_key_image(0, &image);
+    wait_for(&mut app, |_| uploads() == 3);
+
+    // The image is redrawn on the new connection
+    deck.unplug();
+    wait_for(&mut app, |app| streamdeck(app).kind().is_none());
+    deck.plug();
+    streamdeck(&app).rescan();
+    wait_for(&mut app, |_| uploads() == 4);
+    streamdeck(&app).set_key_image(0, &image);
+    wait_for(&mut app, |app| streamdeck(app).skipped_uploads() == 2);
+    assert_eq!(uploads(), 4);
+}